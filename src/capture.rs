@@ -4,7 +4,9 @@ use serde::Deserialize;
 use std::ffi::CString;
 use std::fs::File;
 use std::os::fd::{AsFd, OwnedFd};
+use std::path::Path;
 use std::process::Command;
+use std::sync::OnceLock;
 use wayland_client::{
     Connection, Dispatch, Proxy, QueueHandle,
     globals::{GlobalListContents, registry_queue_init},
@@ -15,6 +17,57 @@ use wayland_protocols_wlr::screencopy::v1::client::{
     zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
 };
 
+// Largest frame side accepted from the compositor, well beyond any real output
+const MAX_FRAME_DIMENSION: u32 = 16384;
+
+/// Decode an sRGB channel value to linear light
+pub fn srgb_to_linear(value: u8) -> f32 {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+    LUT.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let c = i as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })[value as usize]
+}
+
+/// Encode a linear-light value (0.0..=1.0) back to an sRGB channel value
+pub fn linear_to_srgb(value: f32) -> u8 {
+    const STEPS: usize = 4096;
+    static LUT: OnceLock<Vec<u8>> = OnceLock::new();
+    let lut = LUT.get_or_init(|| {
+        (0..STEPS)
+            .map(|i| {
+                let c = i as f32 / (STEPS - 1) as f32;
+                let s = if c <= 0.0031308 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                };
+                (s * 255.0).round() as u8
+            })
+            .collect()
+    });
+    lut[(value.clamp(0.0, 1.0) * (STEPS - 1) as f32).round() as usize]
+}
+
+/// Luminance of an sRGB pixel, as edge detection sees it. By default the Rec. 601 weights
+/// are applied to the gamma-encoded channels; with `linear`, the Rec. 709 weights are
+/// applied in linear light and the result re-encoded, which weighs saturated colors
+/// closer to how bright they look.
+fn pixel_luminance(r: u8, g: u8, b: u8, linear: bool) -> u8 {
+    if linear {
+        return linear_to_srgb(
+            0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b),
+        );
+    }
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as u8
+}
+
 #[derive(Debug, Clone, Copy)]
 struct FrameFormat {
     format: wl_shm::Format,
@@ -23,13 +76,78 @@ struct FrameFormat {
     stride: u32,
 }
 
+impl FrameFormat {
+    /// Validate the compositor-provided dimensions, returning the buffer size in bytes
+    fn buffer_size(&self) -> Result<usize, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!(
+                "Capture frame has zero size ({}x{})",
+                self.width, self.height
+            ));
+        }
+        if self.width > MAX_FRAME_DIMENSION || self.height > MAX_FRAME_DIMENSION {
+            return Err(format!(
+                "Capture frame is too large ({}x{})",
+                self.width, self.height
+            ));
+        }
+        if self.stride < self.width * 4 {
+            return Err(format!(
+                "Capture stride {} is too small for width {}",
+                self.stride, self.width
+            ));
+        }
+        // The shm pool size is an i32
+        (self.stride as usize)
+            .checked_mul(self.height as usize)
+            .filter(|&size| size <= i32::MAX as usize)
+            .ok_or_else(|| {
+                format!(
+                    "Capture buffer is too large (stride {} x {} rows)",
+                    self.stride, self.height
+                )
+            })
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct OutputInfo {
     pub name: Option<String>,
     pub output: Option<wl_output::WlOutput>,
+    /// Current mode size in physical pixels, before the output transform
+    mode: Option<(u32, u32)>,
     done: bool,
 }
 
+/// Region of an output in logical coordinates, written as `WxH+X+Y`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl std::str::FromStr for Region {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (size, position) = s.split_once('+').ok_or(())?;
+        let (width, height) = size.split_once('x').ok_or(())?;
+        let (x, y) = position.split_once('+').ok_or(())?;
+        let region = Region {
+            x: x.parse().map_err(|_| ())?,
+            y: y.parse().map_err(|_| ())?,
+            width: width.parse().map_err(|_| ())?,
+            height: height.parse().map_err(|_| ())?,
+        };
+        if region.width <= 0 || region.height <= 0 {
+            return Err(());
+        }
+        Ok(region)
+    }
+}
+
 struct OutputEnumState {
     outputs: Vec<OutputInfo>,
 }
@@ -85,6 +203,14 @@ impl Dispatch<wl_output::WlOutput, usize> for OutputEnumState {
                 info.name = Some(name);
                 info.output = Some(proxy.clone());
             }
+            wl_output::Event::Mode {
+                flags: wayland_client::WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                info.mode = Some((width as u32, height as u32));
+            }
             wl_output::Event::Done => {
                 info.done = true;
             }
@@ -219,24 +345,197 @@ fn create_shm_fd() -> std::io::Result<OwnedFd> {
     }
 }
 
+/// A captured output. `width` and `height` are the whole output in physical pixels; when
+/// only a region was captured, pixel data covers `data_width` x `data_height` at `origin`
+/// and everything outside it reads as transparent black.
 pub struct Screenshot {
     bgra_data: Vec<u8>,
     pub width: u32,
     pub height: u32,
     luminance: Vec<u8>,
+    origin: (u32, u32),
+    data_width: u32,
+    data_height: u32,
+    /// Name of the captured output, if the compositor reports output names
+    pub output_name: Option<String>,
+    /// Luminance is computed in linear light (see `pixel_luminance`)
+    linear_luminance: bool,
 }
 
 impl Screenshot {
-    pub fn bgra_data(&self) -> &[u8] {
-        &self.bgra_data
+    /// A gray capture from row-major luminance values, for tests
+    #[cfg(test)]
+    pub fn from_gray(width: u32, height: u32, values: &[u8]) -> Self {
+        Screenshot {
+            bgra_data: values.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            width,
+            height,
+            luminance: values.to_vec(),
+            origin: (0, 0),
+            data_width: width,
+            data_height: height,
+            output_name: None,
+            linear_luminance: false,
+        }
+    }
+
+    /// Copy the BGRA pixels `x1..=x2` of row `y` into `dst`, leaving transparent black where
+    /// the row falls outside the captured region
+    pub fn copy_row(&self, y: u32, x1: u32, x2: u32, dst: &mut [u8]) {
+        let (ox, oy) = self.origin;
+        let from = x1.max(ox);
+        let to = x2.min((ox + self.data_width).saturating_sub(1));
+        if y < oy || y >= oy + self.data_height || from > to {
+            dst.fill(0);
+            return;
+        }
+
+        let lead = (from - x1) as usize * 4;
+        let len = (to - from + 1) as usize * 4;
+        let start = ((y - oy) as usize * self.data_width as usize + (from - ox) as usize) * 4;
+        dst[..lead].fill(0);
+        dst[lead..lead + len].copy_from_slice(&self.bgra_data[start..start + len]);
+        dst[lead + len..].fill(0);
     }
 
     pub fn get_luminance(&self, x: u32, y: u32) -> u8 {
-        if x >= self.width || y >= self.height {
+        let (ox, oy) = self.origin;
+        let (Some(x), Some(y)) = (x.checked_sub(ox), y.checked_sub(oy)) else {
+            return 0;
+        };
+        if x >= self.data_width || y >= self.data_height {
             return 0;
         }
-        self.luminance[(y * self.width + x) as usize]
+        self.luminance[(y * self.data_width + x) as usize]
     }
+
+    /// Color of the pixel at (x, y) as `[r, g, b]`, if it was captured
+    pub fn rgb(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        let (ox, oy) = self.origin;
+        let (x, y) = (x.checked_sub(ox)?, y.checked_sub(oy)?);
+        if x >= self.data_width || y >= self.data_height {
+            return None;
+        }
+        let i = (y * self.data_width + x) as usize * 4;
+        let [b, g, r] = self.bgra_data[i..i + 3] else {
+            return None;
+        };
+        Some([r, g, b])
+    }
+
+    /// Average luminance of the square window of `radius` pixels around (x, y)
+    pub fn average_luminance(&self, x: u32, y: u32, radius: u32) -> u8 {
+        let x_range = x.saturating_sub(radius)..=(x + radius).min(self.width.saturating_sub(1));
+        let y_range = y.saturating_sub(radius)..=(y + radius).min(self.height.saturating_sub(1));
+
+        let mut sum = 0u64;
+        let mut count = 0u64;
+        for sy in y_range {
+            for sx in x_range.clone() {
+                sum += self.get_luminance(sx, sy) as u64;
+                count += 1;
+            }
+        }
+        sum.checked_div(count).unwrap_or(0) as u8
+    }
+
+    /// Paint over the inclusive region (x1, y1)..=(x2, y2), blending each row from the pixel
+    /// left of the region to the one right of it. Hides a cursor the compositor left in the
+    /// frame, which would otherwise read as an edge.
+    pub fn mask_region(&mut self, x1: u32, y1: u32, x2: u32, y2: u32) {
+        let (ox, oy) = self.origin;
+        let (x1, y1) = (x1.saturating_sub(ox), y1.saturating_sub(oy));
+        let (Some(x2), Some(y2)) = (x2.checked_sub(ox), y2.checked_sub(oy)) else {
+            return;
+        };
+        let x2 = x2.min(self.data_width.saturating_sub(1));
+        let y2 = y2.min(self.data_height.saturating_sub(1));
+        if x1 > x2 || y1 > y2 {
+            return;
+        }
+
+        // Blend between the nearest pixels outside the region, or the edge pixels inside it
+        // where the region touches the capture's border
+        let left = x1.saturating_sub(1);
+        let right = (x2 + 1).min(self.data_width - 1);
+        let span = (right - left).max(1) as f32;
+        for y in y1..=y2 {
+            let row = (y * self.data_width) as usize;
+            let from = (row + left as usize) * 4;
+            let to = (row + right as usize) * 4;
+            for x in x1..=x2 {
+                let t = (x - left) as f32 / span;
+                let i = (row + x as usize) * 4;
+                for c in 0..3 {
+                    let (a, b) = (
+                        self.bgra_data[from + c] as f32,
+                        self.bgra_data[to + c] as f32,
+                    );
+                    self.bgra_data[i + c] = (a + (b - a) * t).round() as u8;
+                }
+                let [b, g, r] = [
+                    self.bgra_data[i],
+                    self.bgra_data[i + 1],
+                    self.bgra_data[i + 2],
+                ];
+                self.luminance[row + x as usize] = pixel_luminance(r, g, b, self.linear_luminance);
+            }
+        }
+    }
+
+    /// Encode the inclusive region (x1, y1)..=(x2, y2) as a PNG
+    pub fn crop_png(&self, x1: u32, y1: u32, x2: u32, y2: u32) -> Result<Vec<u8>, String> {
+        encode_png(&self.crop(x1, y1, x2, y2)?)
+    }
+
+    /// Encode the region `(x1, y1)..=(x2, y2)` as a PNG with `overlay`, drawn over the
+    /// whole capture from its top-left corner, composited on top
+    pub fn annotated_png(
+        &self,
+        x1: u32,
+        y1: u32,
+        x2: u32,
+        y2: u32,
+        overlay: &tiny_skia::Pixmap,
+    ) -> Result<Vec<u8>, String> {
+        let mut pixmap = self.crop(x1, y1, x2, y2)?;
+        pixmap.draw_pixmap(
+            -(x1 as i32),
+            -(y1 as i32),
+            overlay.as_ref(),
+            &tiny_skia::PixmapPaint::default(),
+            tiny_skia::Transform::identity(),
+            None,
+        );
+        encode_png(&pixmap)
+    }
+
+    /// Copy the region `(x1, y1)..=(x2, y2)`, clamped to the capture, into a pixmap
+    fn crop(&self, x1: u32, y1: u32, x2: u32, y2: u32) -> Result<tiny_skia::Pixmap, String> {
+        let x2 = x2.min(self.width.saturating_sub(1));
+        let y2 = y2.min(self.height.saturating_sub(1));
+        let width = x2.saturating_sub(x1) + 1;
+        let height = y2.saturating_sub(y1) + 1;
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("Empty crop region")?;
+
+        // Captured pixels are opaque and uncaptured ones fully transparent, so BGRA only
+        // needs swizzling into premultiplied RGBA
+        let row_bytes = width as usize * 4;
+        for (y, dst_row) in pixmap.data_mut().chunks_exact_mut(row_bytes).enumerate() {
+            self.copy_row(y1 + y as u32, x1, x2, dst_row);
+            for pixel in dst_row.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixmap)
+    }
+}
+
+fn encode_png(pixmap: &tiny_skia::Pixmap) -> Result<Vec<u8>, String> {
+    pixmap
+        .encode_png()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
 }
 
 #[derive(Deserialize)]
@@ -244,6 +543,22 @@ struct HyprMonitor {
     name: String,
     focused: bool,
     transform: Option<u32>,
+    #[serde(default)]
+    x: i32,
+    #[serde(default)]
+    y: i32,
+    #[serde(default = "default_scale")]
+    scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct HyprWindow {
+    at: (i32, i32),
+    size: (i32, i32),
 }
 
 /// Get monitor info (name, transform) from Hyprland
@@ -253,16 +568,111 @@ pub fn get_focused_monitor_info() -> Option<(String, u32)> {
         .output()
         .ok()?;
     let monitors: Vec<HyprMonitor> = serde_json::from_slice(&output.stdout).ok()?;
-    monitors.into_iter().find(|m| m.focused).map(|m| (m.name, m.transform.unwrap_or(0)))
+    monitors
+        .into_iter()
+        .find(|m| m.focused)
+        .map(|m| (m.name, m.transform.unwrap_or(0)))
+}
+
+/// Names and transforms of all monitors from Hyprland, the focused one first
+pub fn get_monitors() -> Vec<(String, u32)> {
+    let Ok(output) = Command::new("hyprctl").args(["monitors", "-j"]).output() else {
+        return Vec::new();
+    };
+    let mut monitors: Vec<HyprMonitor> = serde_json::from_slice(&output.stdout).unwrap_or_default();
+    monitors.sort_by_key(|m| !m.focused);
+    monitors
+        .into_iter()
+        .map(|m| (m.name, m.transform.unwrap_or(0)))
+        .collect()
+}
+
+/// Scale of the named monitor, or of the focused one, from Hyprland
+pub fn get_monitor_scale(name: Option<&str>) -> Option<f64> {
+    let output = Command::new("hyprctl")
+        .args(["monitors", "-j"])
+        .output()
+        .ok()?;
+    let monitors: Vec<HyprMonitor> = serde_json::from_slice(&output.stdout).ok()?;
+    monitors
+        .into_iter()
+        .find(|m| match name {
+            Some(name) => m.name == name,
+            None => m.focused,
+        })
+        .map(|m| m.scale)
+}
+
+/// Get the focused window's `(x, y, width, height)` in logical pixels relative to the focused
+/// monitor from Hyprland. `zwlr_foreign_toplevel_management_v1` doesn't expose geometry, so
+/// this goes through `hyprctl` like monitor detection.
+pub fn get_active_window_geometry() -> Option<(i32, i32, i32, i32)> {
+    let output = Command::new("hyprctl")
+        .args(["activewindow", "-j"])
+        .output()
+        .ok()?;
+    let window: HyprWindow = serde_json::from_slice(&output.stdout).ok()?;
+
+    let output = Command::new("hyprctl")
+        .args(["monitors", "-j"])
+        .output()
+        .ok()?;
+    let monitors: Vec<HyprMonitor> = serde_json::from_slice(&output.stdout).ok()?;
+    let monitor = monitors.into_iter().find(|m| m.focused)?;
+
+    Some((
+        window.at.0 - monitor.x,
+        window.at.1 - monitor.y,
+        window.size.0,
+        window.size.1,
+    ))
 }
 
-/// Find an output by name, or return the first available
+/// Why a capture failed, by what the user can do about it
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The compositor doesn't offer `zwlr_screencopy_manager_v1`
+    NoScreencopyManager,
+    /// There is no output to capture
+    NoOutput,
+    /// The compositor doesn't offer `wl_shm`
+    NoShm,
+    /// The compositor failed the frame or described one we can't use
+    FrameFailed(String),
+    /// Talking to the compositor or setting up the shared buffer failed
+    Io(String),
+    /// The `--image` file couldn't be read as a PNG
+    Image(String),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CaptureError::NoScreencopyManager => write!(
+                f,
+                "the compositor doesn't support wlr-screencopy, which hypruler needs to capture \
+                 the screen; use a wlroots-based compositor such as Hyprland or Sway \
+                 (capturing through xdg-desktop-portal isn't supported)"
+            ),
+            CaptureError::NoOutput => write!(f, "no output to capture"),
+            CaptureError::NoShm => write!(f, "the compositor doesn't support wl_shm"),
+            CaptureError::FrameFailed(reason) => write!(f, "screen capture failed: {}", reason),
+            CaptureError::Io(reason) => write!(f, "screen capture failed: {}", reason),
+            CaptureError::Image(reason) => write!(f, "failed to load image: {}", reason),
+        }
+    }
+}
+
+/// An output found for capture: its proxy, current mode size and name
+type FoundOutput = (wl_output::WlOutput, Option<(u32, u32)>, Option<String>);
+
+/// Find an output by name, or return the first available, with its current mode size
 fn find_output_by_name(
     conn: &Connection,
     target_name: Option<&str>,
-) -> Result<wl_output::WlOutput, String> {
+) -> Result<FoundOutput, CaptureError> {
     let (globals, mut event_queue) = registry_queue_init::<OutputEnumState>(conn)
-        .map_err(|e| format!("Failed to init registry: {}", e))?;
+        .map_err(|e| CaptureError::Io(format!("failed to init registry: {}", e)))?;
 
     let qh = event_queue.handle();
     let output_globals: Vec<_> = globals
@@ -273,7 +683,7 @@ fn find_output_by_name(
         .collect();
 
     if output_globals.is_empty() {
-        return Err("No outputs available".to_string());
+        return Err(CaptureError::NoOutput);
     }
 
     let mut state = OutputEnumState {
@@ -292,7 +702,7 @@ fn find_output_by_name(
     while !state.outputs.iter().all(|o| o.done) {
         event_queue
             .blocking_dispatch(&mut state)
-            .map_err(|e| format!("Dispatch error: {}", e))?;
+            .map_err(|e| CaptureError::Io(format!("dispatch error: {}", e)))?;
     }
 
     // Find by name, or fall back to first
@@ -303,48 +713,97 @@ fn find_output_by_name(
         None
     }
     .or_else(|| outputs.next())
-    .and_then(|o| o.output);
+    .and_then(|o| Some((o.output?, o.mode, o.name)));
 
-    output.ok_or_else(|| "No output found".to_string())
+    output.ok_or(CaptureError::NoOutput)
 }
 
+/// Load a PNG (e.g. a design mockup) to measure in place of a capture. Transparent parts
+/// are shown over black, as an output would.
+pub fn load_image(path: &Path, linear_luminance: bool) -> Result<Screenshot, CaptureError> {
+    let pixmap = tiny_skia::Pixmap::load_png(path)
+        .map_err(|e| CaptureError::Image(format!("{}: {}", path.display(), e)))?;
+    let (width, height) = (pixmap.width(), pixmap.height());
+
+    // Premultiplied RGBA is already the color over black, so only needs swizzling to BGRA
+    let mut luminance = Vec::with_capacity(pixmap.pixels().len());
+    let mut bgra_data = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let (r, g, b) = (pixel.red(), pixel.green(), pixel.blue());
+        luminance.push(pixel_luminance(r, g, b, linear_luminance));
+        bgra_data.extend([b, g, r, 255]);
+    }
+
+    Ok(Screenshot {
+        bgra_data,
+        width,
+        height,
+        luminance,
+        origin: (0, 0),
+        data_width: width,
+        data_height: height,
+        output_name: None,
+        linear_luminance,
+    })
+}
+
+/// Capture the target output, or only `region` of it (in logical output coordinates) to
+/// save memory and conversion time on large displays. `linear_luminance` picks how edge
+/// detection's luminance is computed (see `pixel_luminance`).
 pub fn capture_screen(
     conn: &Connection,
     target_name: Option<&str>,
     transform: u32,
-) -> Result<Screenshot, String> {
+    region: Option<Region>,
+    linear_luminance: bool,
+) -> Result<Screenshot, CaptureError> {
     // First, find the target output
-    let output = find_output_by_name(conn, target_name)?;
+    let (output, mode, output_name) = find_output_by_name(conn, target_name)?;
 
     let (globals, mut event_queue) = registry_queue_init::<CaptureState>(conn)
-        .map_err(|e| format!("Failed to init registry: {}", e))?;
+        .map_err(|e| CaptureError::Io(format!("failed to init registry: {}", e)))?;
 
     let qh = event_queue.handle();
     let mut state = CaptureState::new();
 
     let screencopy_manager: ZwlrScreencopyManagerV1 = globals
         .bind(&qh, 3..=3, ())
-        .map_err(|_| "wlr-screencopy protocol not available. Is your compositor wlroots-based?")?;
+        .map_err(|_| CaptureError::NoScreencopyManager)?;
 
     let shm: wl_shm::WlShm = globals
         .bind(&qh, 1..=1, ())
-        .map_err(|_| "wl_shm not available")?;
+        .map_err(|_| CaptureError::NoShm)?;
 
-    let frame = screencopy_manager.capture_output(0, &output, &qh, ());
+    let frame = match region {
+        Some(r) => screencopy_manager.capture_output_region(
+            0,
+            &output,
+            r.x,
+            r.y,
+            r.width,
+            r.height,
+            &qh,
+            (),
+        ),
+        None => screencopy_manager.capture_output(0, &output, &qh, ()),
+    };
 
     while !state.done {
         event_queue
             .blocking_dispatch(&mut state)
-            .map_err(|e| format!("Dispatch error: {}", e))?;
+            .map_err(|e| CaptureError::Io(format!("dispatch error: {}", e)))?;
     }
 
-    let format = state.format.ok_or("No suitable buffer format received")?;
+    let format = state.format.ok_or(CaptureError::FrameFailed(
+        "no suitable buffer format received".to_string(),
+    ))?;
+    let size = format.buffer_size().map_err(CaptureError::FrameFailed)?;
 
-    let fd = create_shm_fd().map_err(|e| format!("Failed to create shm fd: {}", e))?;
+    let fd =
+        create_shm_fd().map_err(|e| CaptureError::Io(format!("failed to create shm fd: {}", e)))?;
     let file = File::from(fd);
-    let size = (format.stride * format.height) as u64;
-    file.set_len(size)
-        .map_err(|e| format!("Failed to set file size: {}", e))?;
+    file.set_len(size as u64)
+        .map_err(|e| CaptureError::Io(format!("failed to set file size: {}", e)))?;
 
     let shm_pool = shm.create_pool(file.as_fd(), size as i32, &qh, ());
     let buffer = shm_pool.create_buffer(
@@ -362,14 +821,17 @@ pub fn capture_screen(
     while !state.ready && !state.failed {
         event_queue
             .blocking_dispatch(&mut state)
-            .map_err(|e| format!("Dispatch error: {}", e))?;
+            .map_err(|e| CaptureError::Io(format!("dispatch error: {}", e)))?;
     }
 
     if state.failed {
-        return Err("Screen capture failed".to_string());
+        return Err(CaptureError::FrameFailed(
+            "the compositor reported the frame as failed".to_string(),
+        ));
     }
 
-    let mmap = unsafe { MmapMut::map_mut(&file) }.map_err(|e| format!("Failed to mmap: {}", e))?;
+    let mmap = unsafe { MmapMut::map_mut(&file) }
+        .map_err(|e| CaptureError::Io(format!("failed to mmap: {}", e)))?;
     let data = mmap.to_vec();
 
     // Pre-compute luminance and convert to BGRA in one pass
@@ -393,7 +855,7 @@ pub fn capture_screen(
                     _ => (data[src_idx + 2], data[src_idx + 1], data[src_idx]),
                 };
 
-                luminance[dst_idx] = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as u8;
+                luminance[dst_idx] = pixel_luminance(r, g, b, linear_luminance);
 
                 let bgra_idx = dst_idx * 4;
                 bgra_data[bgra_idx] = b;
@@ -475,10 +937,145 @@ pub fn capture_screen(
     shm_pool.destroy();
     frame.destroy();
 
+    // Place a region capture within the whole output, converting its logical position
+    // with the scale implied by the captured size
+    let (origin, width, height) = match (region, mode) {
+        (Some(r), Some((mode_w, mode_h))) => {
+            let scale = final_width as f64 / r.width as f64;
+            let origin = (
+                (r.x.max(0) as f64 * scale).round() as u32,
+                (r.y.max(0) as f64 * scale).round() as u32,
+            );
+            let (width, height) = match transform {
+                1 | 3 => (mode_h, mode_w),
+                _ => (mode_w, mode_h),
+            };
+            (
+                origin,
+                width.max(origin.0 + final_width),
+                height.max(origin.1 + final_height),
+            )
+        }
+        _ => ((0, 0), final_width, final_height),
+    };
+
     Ok(Screenshot {
         bgra_data: final_bgra,
-        width: final_width,
-        height: final_height,
+        width,
+        height,
         luminance: final_luminance,
+        origin,
+        data_width: final_width,
+        data_height: final_height,
+        output_name,
+        linear_luminance,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, stride: u32) -> FrameFormat {
+        FrameFormat {
+            format: wl_shm::Format::Xrgb8888,
+            width,
+            height,
+            stride,
+        }
+    }
+
+    #[test]
+    fn buffer_size_accepts_normal_frame() {
+        assert_eq!(frame(1920, 1080, 7680).buffer_size(), Ok(7680 * 1080));
+    }
+
+    #[test]
+    fn buffer_size_rejects_zero_size() {
+        assert!(frame(0, 1080, 0).buffer_size().is_err());
+        assert!(frame(1920, 0, 7680).buffer_size().is_err());
+    }
+
+    #[test]
+    fn buffer_size_rejects_oversized_frame() {
+        assert!(
+            frame(MAX_FRAME_DIMENSION + 1, 1080, u32::MAX)
+                .buffer_size()
+                .is_err()
+        );
+        assert!(frame(1920, u32::MAX, 7680).buffer_size().is_err());
+        // Dimensions in range, but the stride pushes the buffer past the shm pool limit
+        assert!(frame(1920, 16384, u32::MAX).buffer_size().is_err());
+    }
+
+    #[test]
+    fn load_image_reads_png_as_opaque_bgra() {
+        let mut pixmap = tiny_skia::Pixmap::new(2, 1).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(200, 100, 50, 255));
+        pixmap.pixels_mut()[1] = tiny_skia::PremultipliedColorU8::TRANSPARENT;
+        let path = std::env::temp_dir().join(format!("hypruler-test-{}.png", std::process::id()));
+        std::fs::write(&path, pixmap.encode_png().unwrap()).unwrap();
+        let screenshot = load_image(&path, false);
+        std::fs::remove_file(&path).unwrap();
+
+        let screenshot = screenshot.unwrap();
+        assert_eq!((screenshot.width, screenshot.height), (2, 1));
+        assert_eq!(screenshot.rgb(0, 0), Some([200, 100, 50]));
+        // Transparent pixels show as black
+        assert_eq!(screenshot.rgb(1, 0), Some([0, 0, 0]));
+        assert_eq!(
+            screenshot.get_luminance(0, 0),
+            pixel_luminance(200, 100, 50, false)
+        );
+    }
+
+    #[test]
+    fn linear_luminance_weighs_saturated_colors_by_brightness() {
+        // Grays are unchanged, while saturated red weighs far more in linear light
+        assert_eq!(pixel_luminance(128, 128, 128, false), 128);
+        assert_eq!(pixel_luminance(128, 128, 128, true), 128);
+        assert!(pixel_luminance(255, 0, 0, true) > pixel_luminance(255, 0, 0, false) + 40);
+        assert!(pixel_luminance(0, 0, 255, true) < pixel_luminance(0, 255, 0, true));
+    }
+
+    #[test]
+    fn mask_region_blends_across_rows() {
+        // A dark square on a black-to-white gradient
+        let (width, height) = (6, 3);
+        let mut bgra_data = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let v = if (2..=3).contains(&x) {
+                    0
+                } else {
+                    x as u8 * 50
+                };
+                bgra_data.extend([v, v, v, 255]);
+            }
+        }
+        let luminance = bgra_data.chunks_exact(4).map(|p| p[0]).collect();
+        let mut screenshot = Screenshot {
+            bgra_data,
+            width,
+            height,
+            luminance,
+            origin: (0, 0),
+            data_width: width,
+            data_height: height,
+            output_name: None,
+            linear_luminance: false,
+        };
+
+        screenshot.mask_region(2, 0, 3, 1);
+        assert_eq!(screenshot.rgb(2, 0), Some([100, 100, 100]));
+        assert_eq!(screenshot.rgb(3, 1), Some([150, 150, 150]));
+        assert_eq!(screenshot.get_luminance(3, 1), 150);
+        // Rows outside the region are untouched
+        assert_eq!(screenshot.rgb(2, 2), Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn buffer_size_rejects_short_stride() {
+        assert!(frame(1920, 1080, 1920).buffer_size().is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Which selections copied text is placed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyTarget {
+    /// The regular clipboard (Ctrl+V)
+    Clipboard,
+    /// The primary selection (middle-click paste)
+    Primary,
+    Both,
+}
+
+impl std::str::FromStr for CopyTarget {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "clipboard" => Ok(CopyTarget::Clipboard),
+            "primary" => Ok(CopyTarget::Primary),
+            "both" => Ok(CopyTarget::Both),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Copy `text` to the chosen Wayland selections through `wl-copy`
+pub fn copy_text(text: &str, target: CopyTarget) -> Result<(), String> {
+    copy(text.as_bytes(), None, target)
+}
+
+/// Copy an encoded PNG to the chosen Wayland selections through `wl-copy`
+pub fn copy_png(png: &[u8], target: CopyTarget) -> Result<(), String> {
+    copy(png, Some("image/png"), target)
+}
+
+fn copy(data: &[u8], mime_type: Option<&str>, target: CopyTarget) -> Result<(), String> {
+    if target != CopyTarget::Primary {
+        wl_copy(data, mime_type, false)?;
+    }
+    if target != CopyTarget::Clipboard {
+        wl_copy(data, mime_type, true)?;
+    }
+    Ok(())
+}
+
+fn wl_copy(data: &[u8], mime_type: Option<&str>, primary: bool) -> Result<(), String> {
+    let mut command = Command::new("wl-copy");
+    if primary {
+        command.arg("--primary");
+    }
+    if let Some(mime_type) = mime_type {
+        command.args(["--type", mime_type]);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run wl-copy: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("wl-copy has no stdin")?
+        .write_all(data)
+        .map_err(|e| format!("Failed to write to wl-copy: {}", e))?;
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("wl-copy exited with {}", status));
+    }
+    Ok(())
+}
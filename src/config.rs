@@ -0,0 +1,375 @@
+use crate::capture::Region;
+use crate::clipboard::CopyTarget;
+use crate::edge_detection::{DEFAULT_MAX_SCAN, ScanOptions};
+use crate::keybinds::{KeyBind, MouseButton};
+use crate::ui::{BarPosition, CrosshairStyle, RealUnit};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Highest `--live` rate; faster than any display refreshes, and keeps the interval non-zero
+const MAX_LIVE_FPS: u32 = 240;
+
+const USAGE: &str = "\
+Usage: hypruler [bench|daemon] [OPTIONS]
+
+Commands:
+  bench                      Time edge detection on one capture without showing the overlay
+  daemon                     Answer measurement requests over a Unix socket without an overlay
+
+Options:
+  --grid <px>                Grid size in logical pixels used when grid snapping is on (default: 8)
+  --timeout <secs>           Exit after this many seconds without pointer or keyboard input
+  --geometry                 Print the selection as `WxH+X+Y` in logical pixels on exit
+  --rem-base <px>            Root font size in logical pixels for the rem unit (default: 16)
+  --region <geom>            Capture only this `WxH+X+Y` region of the output (logical pixels)
+  --crosshair <style>        Cursor marker: cross, dot or lines (default: last used, else cross)
+  --mode <mode>              Measure with auto, edges (no dragging) or drag (no edge detection)
+  --measure-button <button>  Button that drags to measure: left, right or middle (default: left)
+  --dismiss-button <button>  Button that cancels a drag (default: right)
+  --exit-button <button>     Button that exits when not dragging (default: none)
+  --save-dir <dir>           Directory cropped PNGs are saved to (default: ~/Pictures)
+  --dump-capture <path>      Save the raw capture as `<path>-WxH.png` and exit (for debugging)
+  --band <px>                Median each edge over this many scanlines either side of the cursor (default: 0)
+  --min-size <px>            Scan past edges that would make a span smaller than this (logical pixels)
+  --max-scan <px>            Farthest to scan for an edge in each direction, in device pixels (default: 2000)
+  --target <WxH>             Highlight measurements matching this size in logical pixels (within 1px)
+  --no-pick-output           Measure the focused output rather than clicking one when there are several
+  --live <fps>               Keep re-capturing the screen up to this many times per second (1-240)
+  --click-through            Pass clicks to the windows below unless Ctrl is held to measure
+  --status-bar <edge>        Read out position, size and color in a strip at the top or bottom
+  --copy-to <selection>      Where copies go: clipboard, primary or both (default: clipboard)
+  --image <png>              Measure a PNG (e.g. a mockup) instead of capturing the screen
+  --compare <png>            Highlight what changed since an earlier capture (e.g. from --dump-capture)
+  --dpi <n>                  Monitor density in device pixels per inch, to show sizes in mm too
+  --monitor-mm <WxH>         Monitor's physical size in mm, instead of --dpi (e.g. 344x194)
+  --real-unit <unit>         Real-world unit with --dpi or --monitor-mm: mm or in (default: mm)
+  --mask-cursor              Paint over a cursor the compositor left in the capture
+  --socket <path>            Socket for daemon (default: $XDG_RUNTIME_DIR/hypruler.sock)
+  --adaptive                 Scale the edge threshold with the contrast around the cursor
+  --linear-luminance         Detect edges on luminance from linear light, for saturated colors
+  --output <name>            Measure this output (e.g. DP-1) instead of the focused one
+  --watch-file <path>        Keep the current measurement (WxH+X+Y) in a file (follow it with `tail -F`) or named pipe
+  --notify                   Show the final selection in a desktop notification on exit
+  --bind <action>=<key>      Move a shortcut to another key, e.g. quit=x or copy-color=c (repeatable)
+  -h, --help                 Print this help
+";
+
+/// Runtime options parsed from the command line
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub grid_size: u32,
+    pub timeout: Option<Duration>,
+    pub print_geometry: bool,
+    pub rem_base: f64,
+    pub region: Option<Region>,
+    /// Overrides the crosshair style remembered from the last run
+    pub crosshair: Option<CrosshairStyle>,
+    /// Overrides the measuring mode remembered from the last run
+    pub mode: Option<Mode>,
+    pub measure_button: MouseButton,
+    pub dismiss_button: MouseButton,
+    /// Mouse button that exits the overlay; unset so a stray click doesn't close it
+    pub exit_button: Option<MouseButton>,
+    pub save_dir: Option<PathBuf>,
+    pub dump_capture: Option<PathBuf>,
+    /// Scanlines either side of the cursor's row/column that edge detection takes the median of
+    pub band: u32,
+    /// Smallest span in logical pixels edge detection settles on, skipping nearer edges
+    pub min_size: u32,
+    /// Farthest in physical pixels edge detection scans from the cursor in each direction
+    pub max_scan: u32,
+    /// Size measurements are checked against
+    pub target: Option<Size>,
+    /// With several outputs and no `--output`, choose one by clicking it instead of using
+    /// the focused one
+    pub pick_output: bool,
+    /// Run the edge detection benchmark instead of the overlay
+    pub bench: bool,
+    /// Serve measurements over a socket instead of showing the overlay
+    pub daemon: bool,
+    pub socket: Option<PathBuf>,
+    /// Re-capture rate for measuring animated content
+    pub live_fps: Option<u32>,
+    /// Let pointer input through to the windows below except while Ctrl is held
+    pub click_through: bool,
+    /// Fixed readout strip shown instead of the floating measurement label
+    pub status_bar: Option<BarPosition>,
+    /// Selections copied colors and measurements are placed in
+    pub copy_target: CopyTarget,
+    /// Earlier capture the screen is diffed against
+    pub compare: Option<PathBuf>,
+    /// PNG measured in place of the screen capture
+    pub image: Option<PathBuf>,
+    /// Shortcuts moved with `--bind`, replacing their actions' default keys
+    pub keymap: Vec<KeyBind>,
+    pub notify: bool,
+    /// File rewritten with the current measurement whenever it changes
+    pub watch_file: Option<PathBuf>,
+    /// Output to capture and show the overlay on, by name
+    pub output: Option<String>,
+    /// Follow local contrast for what counts as an edge, instead of any luminance change
+    pub adaptive: bool,
+    /// Compute edge detection's luminance in linear light rather than on sRGB values
+    pub linear_luminance: bool,
+    /// Paint over the cursor in captures, for compositors that include it regardless
+    pub mask_cursor: bool,
+    /// Device pixels per inch, for showing real-world sizes
+    pub dpi: Option<f64>,
+    /// Physical monitor size, for showing real-world sizes without knowing the DPI
+    pub monitor_mm: Option<MonitorSize>,
+    pub real_unit: RealUnit,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            grid_size: 8,
+            timeout: None,
+            print_geometry: false,
+            rem_base: 16.0,
+            region: None,
+            crosshair: None,
+            mode: None,
+            measure_button: MouseButton::Left,
+            dismiss_button: MouseButton::Right,
+            exit_button: None,
+            save_dir: None,
+            dump_capture: None,
+            band: 0,
+            min_size: 0,
+            max_scan: DEFAULT_MAX_SCAN,
+            target: None,
+            pick_output: true,
+            bench: false,
+            daemon: false,
+            socket: None,
+            live_fps: None,
+            click_through: false,
+            status_bar: None,
+            copy_target: CopyTarget::Clipboard,
+            compare: None,
+            image: None,
+            keymap: Vec::new(),
+            notify: false,
+            watch_file: None,
+            output: None,
+            adaptive: false,
+            linear_luminance: false,
+            mask_cursor: false,
+            dpi: None,
+            monitor_mm: None,
+            real_unit: RealUnit::Millimeters,
+        }
+    }
+}
+
+impl Config {
+    /// Parse options from the process arguments.
+    /// Prints usage and exits on `--help`.
+    pub fn from_args() -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = std::env::args().skip(1).peekable();
+        match args
+            .next_if(|arg| arg == "bench" || arg == "daemon")
+            .as_deref()
+        {
+            Some("bench") => config.bench = true,
+            Some("daemon") => config.daemon = true,
+            _ => {}
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--grid" => config.grid_size = parse_value(&arg, args.next())?,
+                "--timeout" => {
+                    let secs: u64 = parse_value(&arg, args.next())?;
+                    config.timeout = Some(Duration::from_secs(secs));
+                }
+                "--geometry" => config.print_geometry = true,
+                "--notify" => config.notify = true,
+                "--watch-file" => config.watch_file = Some(parse_value(&arg, args.next())?),
+                "--output" => config.output = Some(parse_value(&arg, args.next())?),
+                "--adaptive" => config.adaptive = true,
+                "--linear-luminance" => config.linear_luminance = true,
+                "--socket" => config.socket = Some(parse_value(&arg, args.next())?),
+                "--mask-cursor" => config.mask_cursor = true,
+                "--dpi" => config.dpi = Some(parse_value(&arg, args.next())?),
+                "--monitor-mm" => config.monitor_mm = Some(parse_value(&arg, args.next())?),
+                "--real-unit" => config.real_unit = parse_value(&arg, args.next())?,
+                "--rem-base" => config.rem_base = parse_value(&arg, args.next())?,
+                "--region" => config.region = Some(parse_value(&arg, args.next())?),
+                "--crosshair" => config.crosshair = Some(parse_value(&arg, args.next())?),
+                "--mode" => config.mode = Some(parse_value(&arg, args.next())?),
+                "--measure-button" => config.measure_button = parse_value(&arg, args.next())?,
+                "--dismiss-button" => config.dismiss_button = parse_value(&arg, args.next())?,
+                "--exit-button" => config.exit_button = Some(parse_value(&arg, args.next())?),
+                "--save-dir" => config.save_dir = Some(parse_value(&arg, args.next())?),
+                "--dump-capture" => config.dump_capture = Some(parse_value(&arg, args.next())?),
+                "--band" => config.band = parse_value(&arg, args.next())?,
+                "--min-size" => config.min_size = parse_value(&arg, args.next())?,
+                "--max-scan" => config.max_scan = parse_value(&arg, args.next())?,
+                "--target" => config.target = Some(parse_value(&arg, args.next())?),
+                "--no-pick-output" => config.pick_output = false,
+                "--live" => config.live_fps = Some(parse_value(&arg, args.next())?),
+                "--click-through" => config.click_through = true,
+                "--status-bar" => config.status_bar = Some(parse_value(&arg, args.next())?),
+                "--copy-to" => config.copy_target = parse_value(&arg, args.next())?,
+                "--compare" => config.compare = Some(parse_value(&arg, args.next())?),
+                "--image" => config.image = Some(parse_value(&arg, args.next())?),
+                "--bind" => config.keymap.push(parse_value(&arg, args.next())?),
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument '{}'\n\n{}", arg, USAGE)),
+            }
+        }
+
+        if config.grid_size == 0 {
+            return Err("--grid must be greater than 0".to_string());
+        }
+        if config.timeout == Some(Duration::ZERO) {
+            return Err("--timeout must be greater than 0".to_string());
+        }
+        if config.max_scan == 0 {
+            return Err("--max-scan must be greater than 0".to_string());
+        }
+        if config
+            .live_fps
+            .is_some_and(|fps| fps == 0 || fps > MAX_LIVE_FPS)
+        {
+            return Err(format!("--live must be between 1 and {}", MAX_LIVE_FPS));
+        }
+        if config.measure_button == config.dismiss_button {
+            return Err("--measure-button and --dismiss-button must differ".to_string());
+        }
+        if config.exit_button == Some(config.measure_button) {
+            return Err("--measure-button and --exit-button must differ".to_string());
+        }
+        if !config.rem_base.is_finite() || config.rem_base <= 0.0 {
+            return Err("--rem-base must be greater than 0".to_string());
+        }
+        if config.dpi.is_some_and(|dpi| !dpi.is_finite() || dpi <= 0.0) {
+            return Err("--dpi must be greater than 0".to_string());
+        }
+        if config.image.is_some() && config.region.is_some() {
+            return Err("--image can't be used with --region".to_string());
+        }
+        if config.dpi.is_some() && config.monitor_mm.is_some() {
+            return Err("--dpi and --monitor-mm can't be used together".to_string());
+        }
+
+        Ok(config)
+    }
+
+    /// Directory for saved PNGs: `--save-dir`, else `~/Pictures`, else the working directory
+    pub fn save_dir(&self) -> PathBuf {
+        self.save_dir
+            .clone()
+            .or_else(|| {
+                let pictures = PathBuf::from(std::env::var_os("HOME")?).join("Pictures");
+                pictures.is_dir().then_some(pictures)
+            })
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Edge detection options, with `--min-size` in physical pixels of an output with the
+    /// given scale
+    pub fn scan_options(&self, scale: f64) -> ScanOptions {
+        ScanOptions {
+            band: self.band,
+            min_size: (self.min_size as f64 * scale).round() as u32,
+            adaptive: self.adaptive,
+            max_distance: self.max_scan,
+        }
+    }
+}
+
+/// Which ways of measuring are active
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Edge detection around the cursor, and dragging to measure a rectangle
+    #[default]
+    Auto,
+    /// Only edge detection; the measure button does nothing
+    Edges,
+    /// Only dragging, with a bare crosshair in between
+    Drag,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Auto => "auto",
+            Mode::Edges => "edges",
+            Mode::Drag => "drag",
+        }
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        [Mode::Auto, Mode::Edges, Mode::Drag]
+            .into_iter()
+            .find(|mode| mode.name() == s)
+            .ok_or(())
+    }
+}
+
+/// A `WxH` size in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for Size {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (width, height) = s.split_once('x').ok_or(())?;
+        let size = Size {
+            width: width.parse().map_err(|_| ())?,
+            height: height.parse().map_err(|_| ())?,
+        };
+        if size.width == 0 || size.height == 0 {
+            return Err(());
+        }
+        Ok(size)
+    }
+}
+
+/// A monitor's physical `WxH` size in millimeters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl std::str::FromStr for MonitorSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (width, height) = s.split_once('x').ok_or(())?;
+        let size = MonitorSize {
+            width: width.parse().map_err(|_| ())?,
+            height: height.parse().map_err(|_| ())?,
+        };
+        let valid = |mm: f64| mm.is_finite() && mm > 0.0;
+        if !valid(size.width) || !valid(size.height) {
+            return Err(());
+        }
+        Ok(size)
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, flag))
+}
@@ -0,0 +1,262 @@
+use crate::capture::{CaptureError, Screenshot, capture_screen, load_image};
+use crate::config::Config;
+use crate::edge_detection::{ScanOptions, find_edges};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use wayland_client::Connection;
+
+// How long a client may go without sending a request before it is disconnected
+const CLIENT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A command read from a daemon client, one per line
+#[derive(Debug, PartialEq)]
+enum Request {
+    /// `measure X Y`: the edges around a point, in physical pixels of the capture
+    Measure(u32, u32),
+    /// `recapture`: replace the cached capture with a fresh one
+    Recapture,
+    /// `quit`: stop the daemon
+    Quit,
+}
+
+impl std::str::FromStr for Request {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut words = s.split_whitespace();
+        let request = match words.next().ok_or(())? {
+            "measure" => {
+                let x = words.next().ok_or(())?.parse().map_err(|_| ())?;
+                let y = words.next().ok_or(())?.parse().map_err(|_| ())?;
+                Request::Measure(x, y)
+            }
+            "recapture" => Request::Recapture,
+            "quit" => Request::Quit,
+            _ => return Err(()),
+        };
+        match words.next() {
+            Some(_) => Err(()),
+            None => Ok(request),
+        }
+    }
+}
+
+/// Reply to `measure`: the element box around the point, with inclusive edges
+#[derive(Serialize)]
+struct Measurement {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    left: u32,
+    up: u32,
+    right: u32,
+    down: u32,
+}
+
+/// Reply to `recapture`: the size of the fresh capture
+#[derive(Serialize)]
+struct CaptureSize {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    error: String,
+}
+
+/// Socket the daemon listens on: `--socket`, else `$XDG_RUNTIME_DIR/hypruler.sock`
+fn socket_path(config: &Config) -> PathBuf {
+    config.socket.clone().unwrap_or_else(|| {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("hypruler.sock")
+    })
+}
+
+/// Serve measurements of a cached capture over a Unix socket until a client sends `quit`.
+/// Clients are handled one at a time; each line they send gets one line of JSON back.
+/// `scale` is the captured output's, which converts `--min-size` as the overlay does.
+pub fn run(
+    conn: &Connection,
+    mut screenshot: Screenshot,
+    config: &Config,
+    target_name: Option<&str>,
+    transform: u32,
+    scale: f64,
+) -> Result<(), String> {
+    let options = config.scan_options(scale);
+    let path = socket_path(config);
+    remove_stale_socket(&path)?;
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+    eprintln!("hypruler: listening on {}", path.display());
+
+    let mut quit = false;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("hypruler: failed to accept a client: {}", e);
+                continue;
+            }
+        };
+        // Clients are served in turn, so one that goes quiet mustn't hold up the rest
+        if let Err(e) = stream.set_read_timeout(Some(CLIENT_READ_TIMEOUT)) {
+            eprintln!("hypruler: failed to set a client timeout: {}", e);
+            continue;
+        }
+        let mut recapture = || match &config.image {
+            Some(path) => load_image(path, config.linear_luminance),
+            None => capture_screen(
+                conn,
+                target_name,
+                transform,
+                config.region,
+                config.linear_luminance,
+            ),
+        };
+        if let Err(e) = serve(stream, &mut screenshot, &options, &mut recapture, &mut quit) {
+            eprintln!("hypruler: client error: {}", e);
+        }
+        if quit {
+            break;
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Remove a socket left behind by a daemon that is no longer running, which would make bind
+/// fail. Anything else at `path`, such as a regular file or a running daemon's socket, is
+/// left alone and reported.
+fn remove_stale_socket(path: &Path) -> Result<(), String> {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(format!("{} exists and is not a socket", path.display()));
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!(
+            "{} is in use, is another daemon running?",
+            path.display()
+        ));
+    }
+    std::fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// Answer one client's requests until it disconnects or asks the daemon to quit
+fn serve(
+    stream: UnixStream,
+    screenshot: &mut Screenshot,
+    options: &ScanOptions,
+    recapture: &mut dyn FnMut() -> Result<Screenshot, CaptureError>,
+    quit: &mut bool,
+) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match line.parse() {
+            Ok(Request::Measure(x, y)) if x >= screenshot.width || y >= screenshot.height => {
+                failure(format!(
+                    "({}, {}) is outside the {}x{} capture",
+                    x, y, screenshot.width, screenshot.height
+                ))
+            }
+            Ok(Request::Measure(x, y)) => {
+                let edges = find_edges(screenshot, x, y, None, options);
+                to_json(&Measurement {
+                    x: edges.left,
+                    y: edges.up,
+                    width: edges.right - edges.left + 1,
+                    height: edges.down - edges.up + 1,
+                    left: edges.left,
+                    up: edges.up,
+                    right: edges.right,
+                    down: edges.down,
+                })
+            }
+            Ok(Request::Recapture) => match recapture() {
+                Ok(fresh) => {
+                    *screenshot = fresh;
+                    to_json(&CaptureSize {
+                        width: screenshot.width,
+                        height: screenshot.height,
+                    })
+                }
+                Err(e) => failure(e.to_string()),
+            },
+            Ok(Request::Quit) => {
+                *quit = true;
+                writeln!(writer, "{{}}")?;
+                return Ok(());
+            }
+            Err(()) => failure(format!(
+                "unknown request '{}' (expected 'measure X Y', 'recapture' or 'quit')",
+                line
+            )),
+        };
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|e| failure(e.to_string()))
+}
+
+fn failure(error: String) -> String {
+    serde_json::to_string(&Failure { error }).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests() {
+        assert_eq!("measure 10 20".parse(), Ok(Request::Measure(10, 20)));
+        assert_eq!("  recapture ".parse(), Ok(Request::Recapture));
+        assert_eq!("quit".parse(), Ok(Request::Quit));
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        assert!("measure 10".parse::<Request>().is_err());
+        assert!("measure -1 20".parse::<Request>().is_err());
+        assert!("measure 10 20 30".parse::<Request>().is_err());
+        assert!("exit".parse::<Request>().is_err());
+    }
+
+    #[test]
+    fn only_stale_sockets_are_removed() {
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(format!("hypruler-{}-{}", name, std::process::id()));
+
+        let file = path("file");
+        std::fs::write(&file, "notes").unwrap();
+        assert!(remove_stale_socket(&file).is_err());
+        assert!(file.exists());
+        std::fs::remove_file(&file).unwrap();
+
+        let live = path("live.sock");
+        let listener = UnixListener::bind(&live).unwrap();
+        assert!(remove_stale_socket(&live).is_err());
+        drop(listener);
+
+        // The socket file outlives its listener, and nothing answers on it any more
+        assert!(remove_stale_socket(&live).is_ok());
+        assert!(!live.exists());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::capture::Screenshot;
+use std::path::Path;
+use tiny_skia::Pixmap;
+
+// Largest per-channel difference still treated as unchanged, absorbing compression noise
+const DIFF_THRESHOLD: u8 = 16;
+// Changed pixels are grouped into regions on a grid of cells this many pixels across,
+// so nearby changes (e.g. the glyphs of an edited word) become one region
+const DIFF_CELL: u32 = 16;
+
+/// Pixels that differ between the capture and a reference image
+pub struct Diff {
+    /// Row-major map of changed pixels, the capture's width across
+    pub mask: Vec<bool>,
+    /// Inclusive `(x1, y1, x2, y2)` bounds of each group of nearby changes
+    pub regions: Vec<(u32, u32, u32, u32)>,
+}
+
+/// Load a reference capture saved earlier, e.g. with `--dump-capture`
+pub fn load_reference(path: &Path) -> Result<Pixmap, String> {
+    Pixmap::load_png(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))
+}
+
+/// Compare the capture against a same-sized `reference`, pixel by pixel
+pub fn diff(screenshot: &Screenshot, reference: &Pixmap) -> Diff {
+    let (width, height) = (screenshot.width, screenshot.height);
+    let changed = |x: u32, y: u32| {
+        let (Some(rgb), Some(pixel)) = (screenshot.rgb(x, y), reference.pixel(x, y)) else {
+            return false;
+        };
+        let pixel = pixel.demultiply();
+        let old = [pixel.red(), pixel.green(), pixel.blue()];
+        rgb.iter()
+            .zip(old)
+            .any(|(&new, old)| new.abs_diff(old) > DIFF_THRESHOLD)
+    };
+    let mask: Vec<bool> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| changed(x, y))
+        .collect();
+
+    // Tight bounds of the changes within each grid cell
+    let (cells_x, cells_y) = (width.div_ceil(DIFF_CELL), height.div_ceil(DIFF_CELL));
+    let mut cells: Vec<Option<(u32, u32, u32, u32)>> = vec![None; (cells_x * cells_y) as usize];
+    for (i, _) in mask.iter().enumerate().filter(|&(_, &changed)| changed) {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let cell = &mut cells[((y / DIFF_CELL) * cells_x + x / DIFF_CELL) as usize];
+        *cell = Some(match *cell {
+            Some((x1, y1, x2, y2)) => (x1.min(x), y1.min(y), x2.max(x), y2.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+
+    // Flood-fill touching cells (including diagonally) into regions
+    let mut regions = Vec::new();
+    let mut visited = vec![false; cells.len()];
+    for start in 0..cells.len() {
+        if visited[start] || cells[start].is_none() {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut region = cells[start].unwrap();
+        while let Some(index) = stack.pop() {
+            let (cx, cy) = (index as u32 % cells_x, index as u32 / cells_x);
+            let (x1, y1, x2, y2) = cells[index].unwrap();
+            region = (
+                region.0.min(x1),
+                region.1.min(y1),
+                region.2.max(x2),
+                region.3.max(y2),
+            );
+            for ny in cy.saturating_sub(1)..=(cy + 1).min(cells_y - 1) {
+                for nx in cx.saturating_sub(1)..=(cx + 1).min(cells_x - 1) {
+                    let neighbour = (ny * cells_x + nx) as usize;
+                    if !visited[neighbour] && cells[neighbour].is_some() {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+        regions.push(region);
+    }
+
+    Diff { mask, regions }
+}
@@ -1,10 +1,38 @@
 use crate::capture::Screenshot;
+use serde::Serialize;
 
 const EDGE_THRESHOLD: i32 = 1;
+// With adaptive thresholds, the window around the cursor whose luminance range is sampled,
+// and the share of that range a step must exceed to count as an edge
+const ADAPTIVE_WINDOW_RADIUS: u32 = 16;
+const ADAPTIVE_FRACTION: f64 = 0.1;
 const SNAP_THRESHOLD: i32 = 10;
 const SNAP_DISTANCE: u32 = 200;
+// Corners needed to agree on rounding before a radius is reported
+const MIN_ROUNDED_CORNERS: usize = 2;
+// Largest edge movement in pixels the smoother follows immediately
+const SMOOTH_DELTA: u32 = 2;
+// Frames a larger jump must persist before the smoother follows it
+const SMOOTH_STABLE_FRAMES: u32 = 2;
+// Default longest ray scanned per direction in edge mode (`--max-scan`), bounding
+// worst-case latency on flat regions
+pub const DEFAULT_MAX_SCAN: u32 = 2000;
+// Luminance step across an edge from which it counts as crisp rather than weak or gradual
+const CRISP_EDGE_DELTA: u8 = 32;
+// Thickest run of pixels beyond an element's edges still taken as its border
+const MAX_BORDER_WIDTH: u32 = 12;
+// Half the width and height of the window around the cursor searched for text lines
+const TEXT_SCAN_HALF_WIDTH: u32 = 200;
+const TEXT_SCAN_HALF_HEIGHT: u32 = 300;
+// Blank rows bridged within one text line, e.g. between the dot and stem of an `i`
+const TEXT_ROW_MERGE_GAP: u32 = 2;
+// Share of a line's densest row that the baseline row still reaches; descenders fall below it
+const BASELINE_DENSITY: f64 = 0.4;
 
-#[derive(Debug, Clone, Copy)]
+/// Inclusive `(x1, y1, x2, y2)` region edge scans are confined to
+pub type ScanBounds = (u32, u32, u32, u32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Edges {
     pub left: u32,
     pub right: u32,
@@ -12,28 +40,216 @@ pub struct Edges {
     pub down: u32,
 }
 
+/// Luminance step across each edge, on the cursor's row and column. Edges at the screen
+/// border, where nothing was found, have a step of 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeStrength {
+    pub left: u8,
+    pub right: u8,
+    pub up: u8,
+    pub down: u8,
+}
+
+impl EdgeStrength {
+    /// Whether a step is a crisp, trustworthy edge rather than a weak or gradual transition
+    pub fn is_crisp(step: u8) -> bool {
+        step >= CRISP_EDGE_DELTA
+    }
+}
+
+/// A row of text: the first row with ink and its baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLine {
+    pub top: u32,
+    pub baseline: u32,
+}
+
+/// Rows of text found around the cursor, scanned across columns `x1..=x2`
+#[derive(Debug, Clone)]
+pub struct TextLines {
+    pub x1: u32,
+    pub x2: u32,
+    pub lines: Vec<TextLine>,
+}
+
+impl TextLines {
+    /// Median distance between consecutive baselines
+    pub fn line_height(&self) -> Option<u32> {
+        let mut heights: Vec<u32> = self
+            .lines
+            .windows(2)
+            .map(|pair| pair[1].baseline - pair[0].baseline)
+            .collect();
+        heights.sort_unstable();
+        heights.get(heights.len() / 2).copied()
+    }
+
+    /// Median height from the top of a line to its baseline, which is the cap (or ascender)
+    /// height for lines containing capitals
+    pub fn cap_height(&self) -> Option<u32> {
+        let mut heights: Vec<u32> = self
+            .lines
+            .iter()
+            .map(|line| line.baseline - line.top + 1)
+            .collect();
+        heights.sort_unstable();
+        heights.get(heights.len() / 2).copied()
+    }
+}
+
+/// A bordered element: the box inside its border and the box around it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BorderBox {
+    pub inner: Edges,
+    pub outer: Edges,
+}
+
+impl BorderBox {
+    /// Border widths as `(top, right, bottom, left)`, in CSS order
+    pub fn widths(&self) -> (u32, u32, u32, u32) {
+        (
+            self.inner.up - self.outer.up,
+            self.outer.right - self.inner.right,
+            self.outer.down - self.inner.down,
+            self.inner.left - self.outer.left,
+        )
+    }
+}
+
+/// Whitespace runs `(start, end)` (inclusive) beyond each side of the element under the cursor
+#[derive(Debug, Clone, Copy)]
+pub struct Gaps {
+    pub left: Option<(u32, u32)>,
+    pub right: Option<(u32, u32)>,
+    pub up: Option<(u32, u32)>,
+    pub down: Option<(u32, u32)>,
+}
+
+/// Temporal filter for `Edges` that ignores jumps to a different edge until they persist
+/// for a couple of frames, so measurement lines don't jitter during slow pointer movement
+#[derive(Debug, Default)]
+pub struct EdgeSmoother {
+    current: Option<Edges>,
+    candidate: Option<Edges>,
+    candidate_frames: u32,
+}
+
+impl EdgeSmoother {
+    /// Feed this frame's detected edges. Returns the edges to display and whether they
+    /// have settled on the detected ones.
+    pub fn update(&mut self, detected: Edges) -> (Edges, bool) {
+        let Some(current) = self.current else {
+            self.current = Some(detected);
+            return (detected, true);
+        };
+
+        let close = |a: u32, b: u32| a.abs_diff(b) <= SMOOTH_DELTA;
+        let small_move = close(current.left, detected.left)
+            && close(current.right, detected.right)
+            && close(current.up, detected.up)
+            && close(current.down, detected.down);
+
+        if self.candidate == Some(detected) {
+            self.candidate_frames += 1;
+        } else {
+            self.candidate = Some(detected);
+            self.candidate_frames = 1;
+        }
+
+        if small_move || self.candidate_frames >= SMOOTH_STABLE_FRAMES {
+            self.current = Some(detected);
+        }
+        let shown = self.current.unwrap_or(detected);
+        (shown, shown == detected)
+    }
+}
+
+/// How edge scans look for edges, from the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanOptions {
+    /// Scanlines either side of the cursor's row/column each edge is the median of
+    pub band: u32,
+    /// Smallest span in physical pixels to settle on (see `scan_span`)
+    pub min_size: u32,
+    /// Scale the edge threshold with the contrast around the cursor (see `edge_threshold`)
+    pub adaptive: bool,
+    /// Longest ray in physical pixels scanned per direction before settling on the bound
+    pub max_distance: u32,
+}
+
+impl ScanOptions {
+    /// Edge scanning around (x, y) with these options
+    fn edge_mode(&self, screenshot: &Screenshot, x: u32, y: u32) -> ScanMode {
+        ScanMode::Edge {
+            threshold: edge_threshold(screenshot, x, y, self.adaptive),
+            max_distance: self.max_distance,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
-enum Axis {
+pub enum Axis {
     X,
     Y,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum ScanMode {
+    /// Compare against the previous pixel (tracks gradient), return the pixel before the edge.
+    /// Holds the luminance step that counts as an edge and how far to scan for one.
+    Edge { threshold: i32, max_distance: u32 },
+    /// Compare against the start luminance, return the edge pixel itself
+    Snap,
+    /// Compare against the start luminance, return the last pixel of the run
+    Run,
+}
+
+impl ScanMode {
+    fn threshold(self) -> i32 {
+        match self {
+            ScanMode::Edge { threshold, .. } => threshold,
+            ScanMode::Snap => SNAP_THRESHOLD,
+            ScanMode::Run => EDGE_THRESHOLD,
+        }
+    }
+
+    fn max_distance(self) -> u32 {
+        match self {
+            ScanMode::Edge { max_distance, .. } => max_distance,
+            ScanMode::Run => DEFAULT_MAX_SCAN,
+            ScanMode::Snap => SNAP_DISTANCE,
+        }
+    }
+}
+
+/// Inclusive range of positions along `axis` that scans may visit: the screen, narrowed
+/// to `bounds` if given
+fn axis_range(screenshot: &Screenshot, axis: Axis, bounds: Option<ScanBounds>) -> (u32, u32) {
+    match (axis, bounds) {
+        (Axis::X, Some((x1, _, x2, _))) => (x1, x2.min(screenshot.width - 1)),
+        (Axis::Y, Some((_, y1, _, y2))) => (y1, y2.min(screenshot.height - 1)),
+        (Axis::X, None) => (0, screenshot.width - 1),
+        (Axis::Y, None) => (0, screenshot.height - 1),
+    }
+}
+
 /// Generic scan function for edge detection.
 /// Scans along `axis` from starting position, looking for luminance changes.
-/// Returns the pixel position just before the edge (for edge detection mode).
+/// Gives up (returns `None`) after `max_distance` steps or at the screen (or `bounds`) boundary.
 fn scan_for_edge(
     screenshot: &Screenshot,
     start_x: u32,
     start_y: u32,
     axis: Axis,
     direction: i32,
-    threshold: i32,
-    max_distance: Option<u32>,
+    mode: ScanMode,
+    bounds: Option<ScanBounds>,
 ) -> Option<u32> {
-    let (mut pos, fixed, limit) = match axis {
-        Axis::X => (start_x as i32, start_y, screenshot.width as i32),
-        Axis::Y => (start_y as i32, start_x, screenshot.height as i32),
+    let (mut pos, fixed) = match axis {
+        Axis::X => (start_x as i32, start_y),
+        Axis::Y => (start_y as i32, start_x),
     };
+    let (min, max) = axis_range(screenshot, axis, bounds);
 
     let get_lum = |p: i32| -> u8 {
         match axis {
@@ -50,29 +266,19 @@ fn scan_for_edge(
         pos += direction;
         steps += 1;
 
-        if pos < 0 || pos >= limit {
+        if pos < min as i32 || pos > max as i32 || steps > mode.max_distance() {
             return None;
         }
-        if let Some(max) = max_distance {
-            if steps > max {
-                return None;
-            }
-        }
 
         let lum = get_lum(pos) as i32;
 
-        // For snap mode (max_distance set): compare against start luminance
-        // For edge mode: compare against previous pixel (tracks gradient)
-        let diff = if max_distance.is_some() {
-            (lum - start_lum).abs()
-        } else {
-            (lum - prev_lum).abs()
+        let diff = match mode {
+            ScanMode::Snap | ScanMode::Run => (lum - start_lum).abs(),
+            ScanMode::Edge { .. } => (lum - prev_lum).abs(),
         };
 
-        if diff > threshold {
-            // For edge detection: return pixel before the edge
-            // For snap: return the edge pixel itself
-            return Some(if max_distance.is_some() {
+        if diff > mode.threshold() {
+            return Some(if mode == ScanMode::Snap {
                 pos as u32
             } else if direction < 0 {
                 (pos + 1) as u32
@@ -84,49 +290,479 @@ fn scan_for_edge(
     }
 }
 
-pub fn find_edges(screenshot: &Screenshot, cursor_x: u32, cursor_y: u32) -> Edges {
+/// Luminance step between the last pixel of a span at `edge` and the next one out in
+/// `direction` along `axis`, on the line through (x, y); 0 when the span ends at the screen
+fn step_at_edge(
+    screenshot: &Screenshot,
+    x: u32,
+    y: u32,
+    axis: Axis,
+    direction: i32,
+    edge: u32,
+) -> u8 {
+    let (min, max) = axis_range(screenshot, axis, None);
+    let beyond = edge as i32 + direction;
+    if beyond < min as i32 || beyond > max as i32 {
+        return 0;
+    }
+    let lum = |p: u32| match axis {
+        Axis::X => screenshot.get_luminance(p, y),
+        Axis::Y => screenshot.get_luminance(x, p),
+    };
+    lum(edge).abs_diff(lum(beyond as u32))
+}
+
+/// How crisp each of `edges` is where the measurement lines through (x, y) meet it: a
+/// large single-pixel step is a sharp border, a small one a faint or gradual transition
+/// that edge detection may have stopped at early or late
+pub fn edge_strength(screenshot: &Screenshot, edges: &Edges, x: u32, y: u32) -> EdgeStrength {
+    EdgeStrength {
+        left: step_at_edge(screenshot, x, y, Axis::X, -1, edges.left),
+        right: step_at_edge(screenshot, x, y, Axis::X, 1, edges.right),
+        up: step_at_edge(screenshot, x, y, Axis::Y, -1, edges.up),
+        down: step_at_edge(screenshot, x, y, Axis::Y, 1, edges.down),
+    }
+}
+
+/// Luminance step that counts as an edge around (x, y). The fixed `EDGE_THRESHOLD` picks up
+/// the faintest borders; adaptively, it grows with the contrast in the surrounding window,
+/// so noise and gradients in high-contrast content don't stop scans while faint borders on
+/// flat backgrounds still do.
+fn edge_threshold(screenshot: &Screenshot, x: u32, y: u32, adaptive: bool) -> i32 {
+    if !adaptive {
+        return EDGE_THRESHOLD;
+    }
+    let x_range = x.saturating_sub(ADAPTIVE_WINDOW_RADIUS)
+        ..=(x + ADAPTIVE_WINDOW_RADIUS).min(screenshot.width.saturating_sub(1));
+    let y_range = y.saturating_sub(ADAPTIVE_WINDOW_RADIUS)
+        ..=(y + ADAPTIVE_WINDOW_RADIUS).min(screenshot.height.saturating_sub(1));
+    let (mut min, mut max) = (u8::MAX, u8::MIN);
+    for sy in y_range {
+        for sx in x_range.clone() {
+            let lum = screenshot.get_luminance(sx, sy);
+            min = min.min(lum);
+            max = max.max(lum);
+        }
+    }
+    let range = max.saturating_sub(min) as f64;
+    ((range * ADAPTIVE_FRACTION) as i32).max(EDGE_THRESHOLD)
+}
+
+/// Edges `(before, after)` of the span through (x, y) along `axis`, defaulting to the scan
+/// limits where no edge is found. While the span is shorter than `min_size`, the scan
+/// continues past the nearer edge, so stray pixels next to the cursor don't collapse the
+/// measurement.
+fn scan_span(
+    screenshot: &Screenshot,
+    x: u32,
+    y: u32,
+    axis: Axis,
+    bounds: Option<ScanBounds>,
+    min_size: u32,
+    mode: ScanMode,
+) -> (u32, u32) {
+    let limits = axis_range(screenshot, axis, bounds);
+    let scan = |from: u32, direction: i32| {
+        let (sx, sy) = match axis {
+            Axis::X => (from, y),
+            Axis::Y => (x, from),
+        };
+        scan_for_edge(screenshot, sx, sy, axis, direction, mode, bounds)
+    };
+    let cursor = match axis {
+        Axis::X => x,
+        Axis::Y => y,
+    };
+    let mut before = scan(cursor, -1).unwrap_or(limits.0);
+    let mut after = scan(cursor, 1).unwrap_or(limits.1);
+
+    while after - before + 1 < min_size {
+        let can_extend_before = before > limits.0;
+        let can_extend_after = after < limits.1;
+        // Continue past the nearer edge, from the pixel beyond it
+        let extend_before =
+            can_extend_before && (!can_extend_after || cursor - before <= after - cursor);
+        if extend_before {
+            before = scan(before - 1, -1).unwrap_or(limits.0);
+        } else if can_extend_after {
+            after = scan(after + 1, 1).unwrap_or(limits.1);
+        } else {
+            break;
+        }
+    }
+    (before, after)
+}
+
+/// Find the edges around the cursor. Scans stop at `bounds` when the cursor is inside them,
+/// so measurements stay within a chosen container.
+///
+/// With a non-zero `band`, each edge is the median over the scanlines up to `band` pixels
+/// either side of the cursor's row or column, which steadies it on tilted or ragged content.
+/// Spans shorter than `min_size` pixels are widened to the next edges (see `scan_span`).
+/// With `adaptive`, the step counting as an edge follows the local contrast around the
+/// cursor (see `edge_threshold`). No edge within `max_distance` counts as the bound.
+pub fn find_edges(
+    screenshot: &Screenshot,
+    cursor_x: u32,
+    cursor_y: u32,
+    bounds: Option<ScanBounds>,
+    options: &ScanOptions,
+) -> Edges {
+    let bounds = bounds
+        .filter(|&(x1, y1, x2, y2)| (x1..=x2).contains(&cursor_x) && (y1..=y2).contains(&cursor_y));
+    let mode = options.edge_mode(screenshot, cursor_x, cursor_y);
+    let band = options.band;
+    let scan = |axis| {
+        // Scanlines run along `axis`, so the band spreads them across the other one
+        let (lines, (min, max)) = match axis {
+            Axis::X => (cursor_y, axis_range(screenshot, Axis::Y, bounds)),
+            Axis::Y => (cursor_x, axis_range(screenshot, Axis::X, bounds)),
+        };
+        let (mut before, mut after): (Vec<u32>, Vec<u32>) = (lines.saturating_sub(band).max(min)
+            ..=(lines + band).min(max))
+            .map(|line| {
+                let (x, y) = match axis {
+                    Axis::X => (cursor_x, line),
+                    Axis::Y => (line, cursor_y),
+                };
+                scan_span(screenshot, x, y, axis, bounds, options.min_size, mode)
+            })
+            .unzip();
+        before.sort_unstable();
+        after.sort_unstable();
+        (before[before.len() / 2], after[after.len() / 2])
+    };
+
+    let (left, right) = scan(Axis::X);
+    let (up, down) = scan(Axis::Y);
     Edges {
-        left: scan_for_edge(
-            screenshot,
-            cursor_x,
-            cursor_y,
-            Axis::X,
-            -1,
-            EDGE_THRESHOLD,
-            None,
-        )
-        .unwrap_or(0),
-        right: scan_for_edge(
-            screenshot,
-            cursor_x,
-            cursor_y,
-            Axis::X,
-            1,
-            EDGE_THRESHOLD,
-            None,
-        )
-        .unwrap_or(screenshot.width - 1),
-        up: scan_for_edge(
-            screenshot,
-            cursor_x,
-            cursor_y,
-            Axis::Y,
-            -1,
-            EDGE_THRESHOLD,
-            None,
-        )
-        .unwrap_or(0),
-        down: scan_for_edge(
-            screenshot,
-            cursor_x,
-            cursor_y,
-            Axis::Y,
-            1,
-            EDGE_THRESHOLD,
-            None,
-        )
-        .unwrap_or(screenshot.height - 1),
+        left,
+        right,
+        up,
+        down,
+    }
+}
+
+/// Inclusive extent `(start, end)` along `axis` of the run of pixels through (x, y) that
+/// keep the cursor pixel's luminance, i.e. the thickness of a thin element (scrollbar,
+/// divider) the cursor sits on, rather than the span between edges around it
+pub fn measure_run_width(screenshot: &Screenshot, x: u32, y: u32, axis: Axis) -> (u32, u32) {
+    let (min, max) = axis_range(screenshot, axis, None);
+    let scan = |direction| scan_for_edge(screenshot, x, y, axis, direction, ScanMode::Run, None);
+    (scan(-1).unwrap_or(min), scan(1).unwrap_or(max))
+}
+
+/// Edges of the element the cursor sits on, from its run widths across and down
+pub fn find_run_edges(screenshot: &Screenshot, cursor_x: u32, cursor_y: u32) -> Edges {
+    let (left, right) = measure_run_width(screenshot, cursor_x, cursor_y, Axis::X);
+    let (up, down) = measure_run_width(screenshot, cursor_x, cursor_y, Axis::Y);
+    Edges {
+        left,
+        right,
+        up,
+        down,
+    }
+}
+
+/// Find the container enclosing the element bounded by `edges` by re-scanning from just
+/// outside it: the row just above (or below) the element gives the container's width and
+/// the column just left (or right) of it its height. Returns `None` when that finds nothing
+/// larger, i.e. the element already is the outermost region.
+pub fn find_container(
+    screenshot: &Screenshot,
+    edges: &Edges,
+    bounds: Option<ScanBounds>,
+    options: &ScanOptions,
+) -> Option<Edges> {
+    let (min_x, max_x) = axis_range(screenshot, Axis::X, bounds);
+    let (min_y, max_y) = axis_range(screenshot, Axis::Y, bounds);
+    let center_x = edges.left + (edges.right - edges.left) / 2;
+    let center_y = edges.up + (edges.down - edges.up) / 2;
+
+    let outside_y = if edges.up > min_y {
+        Some(edges.up - 1)
+    } else {
+        (edges.down < max_y).then_some(edges.down + 1)
+    };
+    let outside_x = if edges.left > min_x {
+        Some(edges.left - 1)
+    } else {
+        (edges.right < max_x).then_some(edges.right + 1)
+    };
+
+    let mut container = *edges;
+    if let Some(y) = outside_y {
+        let row = find_edges(screenshot, center_x, y, bounds, options);
+        container.left = container.left.min(row.left);
+        container.right = container.right.max(row.right);
+    }
+    if let Some(x) = outside_x {
+        let column = find_edges(screenshot, x, center_y, bounds, options);
+        container.up = container.up.min(column.up);
+        container.down = container.down.max(column.down);
+    }
+    (container != *edges).then_some(container)
+}
+
+/// Every luminance transition along the cursor's row and column, as `(position, delta)`
+/// pairs: the pixel where luminance changed and by how much from the one before it
+#[derive(Debug, Serialize)]
+pub struct EdgeReport {
+    pub x: u32,
+    pub y: u32,
+    pub row: Vec<(u32, i32)>,
+    pub column: Vec<(u32, i32)>,
+}
+
+/// List all the transitions edge mode scans could stop at through (x, y), across the whole
+/// screen, for seeing what detection sees
+pub fn edge_report(screenshot: &Screenshot, x: u32, y: u32) -> EdgeReport {
+    let transitions = |len: u32, lum: &dyn Fn(u32) -> u8| -> Vec<(u32, i32)> {
+        (1..len)
+            .map(|pos| (pos, lum(pos) as i32 - lum(pos - 1) as i32))
+            .filter(|&(_, delta)| delta.abs() > EDGE_THRESHOLD)
+            .collect()
+    };
+    EdgeReport {
+        x,
+        y,
+        row: transitions(screenshot.width, &|pos| screenshot.get_luminance(pos, y)),
+        column: transitions(screenshot.height, &|pos| screenshot.get_luminance(x, pos)),
+    }
+}
+
+/// Row-major map of pixels whose luminance differs from their right or lower neighbour
+/// by more than the edge threshold: the edges `find_edges` can stop at
+pub fn edge_mask(screenshot: &Screenshot) -> Vec<bool> {
+    let (width, height) = (screenshot.width, screenshot.height);
+    let mut mask = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let lum = screenshot.get_luminance(x, y) as i32;
+            let differs = |nx: u32, ny: u32| {
+                (screenshot.get_luminance(nx, ny) as i32 - lum).abs() > EDGE_THRESHOLD
+            };
+            mask.push(
+                (x + 1 < width && differs(x + 1, y)) || (y + 1 < height && differs(x, y + 1)),
+            );
+        }
+    }
+    mask
+}
+
+/// Find rows of text around the cursor for checking line spacing.
+///
+/// Each row of a window around the cursor is classed as ink or background by whether any
+/// pixel differs from the window's most common luminance, so moving down a column of text
+/// alternates between runs of ink rows (text lines) and background. The baseline of each
+/// line is its last row still at least `BASELINE_DENSITY` as dense as its densest one, which
+/// drops sparse descenders.
+pub fn find_text_lines(
+    screenshot: &Screenshot,
+    cursor_x: u32,
+    cursor_y: u32,
+    bounds: Option<ScanBounds>,
+) -> TextLines {
+    let bounds = bounds
+        .filter(|&(x1, y1, x2, y2)| (x1..=x2).contains(&cursor_x) && (y1..=y2).contains(&cursor_y));
+    let (min_x, max_x) = axis_range(screenshot, Axis::X, bounds);
+    let (min_y, max_y) = axis_range(screenshot, Axis::Y, bounds);
+    let x1 = cursor_x.saturating_sub(TEXT_SCAN_HALF_WIDTH).max(min_x);
+    let x2 = (cursor_x + TEXT_SCAN_HALF_WIDTH).min(max_x);
+    let y1 = cursor_y.saturating_sub(TEXT_SCAN_HALF_HEIGHT).max(min_y);
+    let y2 = (cursor_y + TEXT_SCAN_HALF_HEIGHT).min(max_y);
+
+    let mut histogram = [0u32; 256];
+    for y in y1..=y2 {
+        for x in x1..=x2 {
+            histogram[screenshot.get_luminance(x, y) as usize] += 1;
+        }
+    }
+    let background = (0..256).max_by_key(|&l| histogram[l]).unwrap_or(0) as i32;
+
+    // Ink pixels per row
+    let density: Vec<u32> = (y1..=y2)
+        .map(|y| {
+            (x1..=x2)
+                .filter(|&x| {
+                    (screenshot.get_luminance(x, y) as i32 - background).abs() > SNAP_THRESHOLD
+                })
+                .count() as u32
+        })
+        .collect();
+
+    // Runs of ink rows, bridging short blank gaps
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for (i, _) in density.iter().enumerate().filter(|&(_, &count)| count > 0) {
+        let y = y1 + i as u32;
+        match runs.last_mut() {
+            Some((_, end)) if y - *end <= TEXT_ROW_MERGE_GAP + 1 => *end = y,
+            _ => runs.push((y, y)),
+        }
+    }
+
+    // Lines cut off by the window can't be measured
+    let lines = runs
+        .into_iter()
+        .filter(|&(top, bottom)| top > y1 && bottom < y2)
+        .map(|(top, bottom)| {
+            let rows = &density[(top - y1) as usize..=(bottom - y1) as usize];
+            let peak = rows.iter().copied().max().unwrap_or(0) as f64;
+            let last_dense = rows
+                .iter()
+                .rposition(|&count| count as f64 >= peak * BASELINE_DENSITY)
+                .unwrap_or(0);
+            TextLine {
+                top,
+                baseline: top + last_dense as u32,
+            }
+        })
+        .collect();
+
+    TextLines { x1, x2, lines }
+}
+
+/// Find the bounding box of everything on screen that differs from the background, by
+/// scanning rows and then columns inward from each screen edge until one holds content
+/// (e.g. the picture inside a letterboxed video). `None` when the screen is all background.
+pub fn find_content_bounds(
+    screenshot: &Screenshot,
+    background_luminance: u8,
+) -> Option<ScanBounds> {
+    let (width, height) = (screenshot.width, screenshot.height);
+    if width == 0 || height == 0 {
+        return None;
     }
+    let background = background_luminance as i32;
+    let is_content = |x: u32, y: u32| {
+        (screenshot.get_luminance(x, y) as i32 - background).abs() > SNAP_THRESHOLD
+    };
+    let row_has_content = |y: u32| (0..width).any(|x| is_content(x, y));
+    let top = (0..height).find(|&y| row_has_content(y))?;
+    let bottom = (top..height).rev().find(|&y| row_has_content(y))?;
+    let column_has_content = |x: u32| (top..=bottom).any(|y| is_content(x, y));
+    let left = (0..width).find(|&x| column_has_content(x))?;
+    let right = (left..width).rev().find(|&x| column_has_content(x))?;
+    Some((left, top, right, bottom))
+}
+
+/// Find the border around the element bounded by `edges`, whose interior contains the cursor,
+/// by continuing each scan past the element's edge to the next one. The element counts as
+/// bordered when that run is at most `MAX_BORDER_WIDTH` pixels thick on every side.
+pub fn find_border_box(
+    screenshot: &Screenshot,
+    edges: &Edges,
+    cursor_x: u32,
+    cursor_y: u32,
+    bounds: Option<ScanBounds>,
+    options: &ScanOptions,
+) -> Option<BorderBox> {
+    let mode = options.edge_mode(screenshot, cursor_x, cursor_y);
+
+    // The far end of the run starting just past `edge` in `direction`, if it is thin enough
+    let border = |edge: u32, axis: Axis, direction: i32| -> Option<u32> {
+        let (min, max) = axis_range(screenshot, axis, bounds);
+        let start = edge.checked_add_signed(direction)?;
+        if start < min || start > max {
+            return None;
+        }
+        let (x, y) = match axis {
+            Axis::X => (start, cursor_y),
+            Axis::Y => (cursor_x, start),
+        };
+        let end = scan_for_edge(screenshot, x, y, axis, direction, mode, bounds)?;
+        (end.abs_diff(edge) <= MAX_BORDER_WIDTH).then_some(end)
+    };
+
+    let outer = Edges {
+        left: border(edges.left, Axis::X, -1)?,
+        right: border(edges.right, Axis::X, 1)?,
+        up: border(edges.up, Axis::Y, -1)?,
+        down: border(edges.down, Axis::Y, 1)?,
+    };
+    Some(BorderBox {
+        inner: *edges,
+        outer,
+    })
+}
+
+/// Find the spacing between the element under the cursor and its neighbours by
+/// continuing each scan past the element's edge to the next edge.
+pub fn find_gaps(
+    screenshot: &Screenshot,
+    cursor_x: u32,
+    cursor_y: u32,
+    bounds: Option<ScanBounds>,
+    options: &ScanOptions,
+) -> Gaps {
+    let bounds = bounds
+        .filter(|&(x1, y1, x2, y2)| (x1..=x2).contains(&cursor_x) && (y1..=y2).contains(&cursor_y));
+    let edges = find_edges(screenshot, cursor_x, cursor_y, bounds, options);
+    let mode = options.edge_mode(screenshot, cursor_x, cursor_y);
+
+    // The run starting just past `edge` in `direction`, if the edge isn't the scan boundary
+    let gap = |edge: u32, axis: Axis, direction: i32| -> Option<(u32, u32)> {
+        let (min, max) = axis_range(screenshot, axis, bounds);
+        let start = edge.checked_add_signed(direction)?;
+        if start < min || start > max {
+            return None;
+        }
+        let (x, y) = match axis {
+            Axis::X => (start, cursor_y),
+            Axis::Y => (cursor_x, start),
+        };
+        let end = scan_for_edge(screenshot, x, y, axis, direction, mode, bounds)?;
+        Some((start.min(end), start.max(end)))
+    };
+
+    Gaps {
+        left: gap(edges.left, Axis::X, -1),
+        right: gap(edges.right, Axis::X, 1),
+        up: gap(edges.up, Axis::Y, -1),
+        down: gap(edges.down, Axis::Y, 1),
+    }
+}
+
+/// Estimate the corner radius of the element bounded by `edges`, if its corners are rounded.
+///
+/// A rounded corner of radius r leaves the outside color along the diagonal for
+/// r * (1 - 1/sqrt(2)) pixels before reaching the element's fill, so each corner is walked
+/// inward diagonally until its luminance matches the interior under the cursor.
+pub fn estimate_corner_radius(
+    screenshot: &Screenshot,
+    edges: &Edges,
+    cursor_x: u32,
+    cursor_y: u32,
+) -> Option<u32> {
+    let interior = screenshot.get_luminance(cursor_x, cursor_y) as i32;
+    let width = edges.right.saturating_sub(edges.left) + 1;
+    let height = edges.down.saturating_sub(edges.up) + 1;
+    let max_radius = width.min(height) / 2;
+    let max_inset = (max_radius as f64 * (1.0 - std::f64::consts::FRAC_1_SQRT_2)) as u32;
+
+    let corners = [
+        (edges.left, edges.up, 1, 1),
+        (edges.right, edges.down, -1, -1),
+        (edges.right, edges.up, -1, 1),
+        (edges.left, edges.down, 1, -1),
+    ];
+    let radii: Vec<f64> = corners
+        .iter()
+        .filter_map(|&(x, y, dx, dy)| {
+            let inset = (0..=max_inset).find(|&d| {
+                let px = x.saturating_add_signed(dx * d as i32);
+                let py = y.saturating_add_signed(dy * d as i32);
+                (screenshot.get_luminance(px, py) as i32 - interior).abs() <= SNAP_THRESHOLD
+            })?;
+            (inset > 0).then(|| inset as f64 / (1.0 - std::f64::consts::FRAC_1_SQRT_2))
+        })
+        .collect();
+
+    if radii.len() < MIN_ROUNDED_CORNERS {
+        return None;
+    }
+    let radius = radii.iter().sum::<f64>() / radii.len() as f64;
+    Some((radius.round() as u32).min(max_radius))
 }
 
 /// Snap a vertical edge (left or right) to nearby content.
@@ -138,17 +774,7 @@ pub fn snap_edge_x(
     direction: i32,
 ) -> u32 {
     (y_start..=y_end)
-        .filter_map(|y| {
-            scan_for_edge(
-                screenshot,
-                x,
-                y,
-                Axis::X,
-                direction,
-                SNAP_THRESHOLD,
-                Some(SNAP_DISTANCE),
-            )
-        })
+        .filter_map(|y| scan_for_edge(screenshot, x, y, Axis::X, direction, ScanMode::Snap, None))
         .reduce(|a, b| if direction > 0 { a.min(b) } else { a.max(b) })
         .unwrap_or(x)
 }
@@ -162,17 +788,48 @@ pub fn snap_edge_y(
     direction: i32,
 ) -> u32 {
     (x_start..=x_end)
-        .filter_map(|x| {
-            scan_for_edge(
-                screenshot,
-                x,
-                y,
-                Axis::Y,
-                direction,
-                SNAP_THRESHOLD,
-                Some(SNAP_DISTANCE),
-            )
-        })
+        .filter_map(|x| scan_for_edge(screenshot, x, y, Axis::Y, direction, ScanMode::Snap, None))
         .reduce(|a, b| if direction > 0 { a.min(b) } else { a.max(b) })
         .unwrap_or(y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_size_steps_past_stray_lines() {
+        // A light row with a 1px dark line just left of the cursor
+        let mut values = [200u8; 20];
+        values[7] = 0;
+        let screenshot = Screenshot::from_gray(20, 1, &values);
+
+        let mode = ScanMode::Edge {
+            threshold: EDGE_THRESHOLD,
+            max_distance: DEFAULT_MAX_SCAN,
+        };
+        let span = |min_size| scan_span(&screenshot, 9, 0, Axis::X, None, min_size, mode);
+        assert_eq!(span(0), (8, 19));
+        assert_eq!(span(15), (0, 19));
+    }
+
+    #[test]
+    fn scans_stop_at_max_distance() {
+        // A step 6px right of the cursor, out of reach of a 3px scan
+        let mut values = [200u8; 20];
+        values[15..].fill(0);
+        let screenshot = Screenshot::from_gray(20, 1, &values);
+
+        let options = |max_distance| ScanOptions {
+            band: 0,
+            min_size: 0,
+            adaptive: false,
+            max_distance,
+        };
+        assert_eq!(
+            find_edges(&screenshot, 9, 0, None, &options(DEFAULT_MAX_SCAN)).right,
+            14
+        );
+        assert_eq!(find_edges(&screenshot, 9, 0, None, &options(3)).right, 19);
+    }
+}
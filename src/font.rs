@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::process::Command;
+use std::rc::Rc;
+
+use fontdue::Metrics;
+
+// Fontconfig patterns tried in order until one resolves to a font fontdue can parse
+const FONT_PATTERNS: &[&str] = &[
+    "sans-serif",
+    "DejaVu Sans",
+    "Noto Sans",
+    "Liberation Sans",
+    "monospace",
+];
+
+// Well-known locations used when fontconfig is unavailable
+const FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+];
+
+// Bundled fallback so labels render even without any system fonts: DejaVu Sans cut down to
+// Latin-1 and the symbols the labels use
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Subset.ttf");
+
+/// A rasterized glyph: its metrics and coverage bitmap
+pub type Glyph = Rc<(Metrics, Vec<u8>)>;
+
+/// A font with a cache of rasterized glyphs, keyed by character and pixel size, so
+/// labels redrawn on every motion event don't re-rasterize the same digits each frame
+pub struct Font {
+    font: fontdue::Font,
+    glyphs: RefCell<HashMap<(char, u32), Glyph>>,
+}
+
+impl Font {
+    fn new(font: fontdue::Font) -> Self {
+        Self {
+            font,
+            glyphs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Rasterize `c` at `size` pixels, reusing an earlier rasterization when cached
+    pub fn rasterize(&self, c: char, size: f32) -> Glyph {
+        self.glyphs
+            .borrow_mut()
+            .entry((c, size.to_bits()))
+            .or_insert_with(|| Rc::new(self.font.rasterize(c, size)))
+            .clone()
+    }
+
+    /// Horizontal advance of `c` at `size` pixels
+    pub fn advance_width(&self, c: char, size: f32) -> f32 {
+        self.rasterize(c, size).0.advance_width
+    }
+}
+
+fn parse_font(data: Vec<u8>) -> Option<fontdue::Font> {
+    fontdue::Font::from_bytes(data, fontdue::FontSettings::default()).ok()
+}
+
+/// Resolve a fontconfig pattern to a font file path via `fc-match`
+fn fc_match(pattern: &str) -> Option<String> {
+    let output = Command::new("fc-match")
+        .args(["-f", "%{file}", pattern])
+        .output()
+        .ok()?;
+    let path = String::from_utf8(output.stdout).ok()?;
+    let path = path.trim();
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Find a usable sans-serif system font, trying fontconfig first and then common paths,
+/// falling back to the embedded font
+pub fn load_font() -> Option<Font> {
+    FONT_PATTERNS
+        .iter()
+        .filter_map(|pattern| fc_match(pattern))
+        .chain(FONT_PATHS.iter().map(|path| path.to_string()))
+        .find_map(|path| std::fs::read(path).ok().and_then(parse_font))
+        .map(Font::new)
+        .or_else(embedded_font)
+}
+
+/// The bundled font, which renders the same on every system
+pub fn embedded_font() -> Option<Font> {
+    parse_font(EMBEDDED_FONT.to_vec()).map(Font::new)
+}
@@ -0,0 +1,535 @@
+use smithay_client_toolkit::seat::keyboard::Keysym;
+use xkbcommon::xkb;
+
+/// Mouse buttons that can be bound to measuring, dismissing or exiting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    /// Linux input event code (`BTN_LEFT` and friends) reported by `wl_pointer`
+    pub fn code(self) -> u32 {
+        match self {
+            MouseButton::Left => 0x110,
+            MouseButton::Right => 0x111,
+            MouseButton::Middle => 0x112,
+        }
+    }
+}
+
+impl std::str::FromStr for MouseButton {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "left" => Ok(MouseButton::Left),
+            "right" => Ok(MouseButton::Right),
+            "middle" => Ok(MouseButton::Middle),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Something a key press can trigger
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Recapture,
+    CycleContrast,
+    CycleUnit,
+    ToggleGridSnap,
+    ToggleLinearBlend,
+    ToggleAntialias,
+    ToggleRuler,
+    ToggleRoundSizes,
+    CycleLabelCorner,
+    ToggleGaps,
+    ToggleMargins,
+    ToggleBaselines,
+    ToggleBorderBox,
+    ToggleRunWidth,
+    ToggleScreenSize,
+    ToggleContentBounds,
+    ToggleRunningTotal,
+    ResetRunningTotal,
+    SaveCrop,
+    ToggleEdgeMap,
+    ReportEdges,
+    ToggleScanBounds,
+    ToggleSmoothing,
+    ToggleEdgeConfidence,
+    CycleCrosshair,
+    ToggleWindowOrigin,
+    TogglePathMode,
+    CycleMode,
+    ToggleRadial,
+    TogglePin,
+    ToggleExtensionLines,
+    NextElement,
+    PreviousElement,
+    OuterContainer,
+    InnerContainer,
+    CycleColorReadout,
+    CopyColor,
+    EnterSize,
+    TogglePointerLock,
+    TogglePrecision,
+    ToggleLoupe,
+    ToggleNativeOrientation,
+    ToggleHelp,
+    GrowLabels,
+    ShrinkLabels,
+    CopyMeasurement,
+    CopyImage,
+    Quit,
+}
+
+impl Action {
+    /// Name used with `--bind`, the variant in kebab case (e.g. `copy-color`)
+    pub fn name(self) -> String {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_ascii_uppercase() && !name.is_empty() {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        BINDINGS
+            .iter()
+            .chain(CTRL_BINDINGS)
+            .map(|b| b.action)
+            .find(|action| action.name() == s)
+            .ok_or(())
+    }
+}
+
+/// A key given to an action with `--bind <action>=<key>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBind {
+    pub action: Action,
+    pub keysym: Keysym,
+}
+
+impl std::str::FromStr for KeyBind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (action, key) = s.split_once('=').ok_or(())?;
+        Ok(KeyBind {
+            action: action.parse()?,
+            keysym: parse_keysym(key).ok_or(())?,
+        })
+    }
+}
+
+/// Parse a key as an xkb keysym name (`Escape`, `F1`, `q`) or a single character (`=`)
+fn parse_keysym(s: &str) -> Option<Keysym> {
+    let keysym = xkb::keysym_from_name(s, xkb::KEYSYM_NO_FLAGS);
+    if keysym != Keysym::NoSymbol {
+        return Some(keysym);
+    }
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(Keysym::from_char(c)),
+        _ => None,
+    }
+}
+
+/// Key name shown in the help panel for a rebound action
+pub fn key_name(keysym: Keysym) -> String {
+    match keysym.key_char().filter(|c| c.is_ascii_graphic()) {
+        Some(c) => c.to_ascii_uppercase().to_string(),
+        None => xkb::keysym_get_name(keysym),
+    }
+}
+
+pub struct Binding {
+    pub keysym: Keysym,
+    pub action: Action,
+    /// Key name shown in the help panel
+    pub key: &'static str,
+    pub description: &'static str,
+}
+
+/// Single source of truth for keyboard shortcuts, used by both input handling and the help panel
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        keysym: Keysym::r,
+        action: Action::Recapture,
+        key: "R",
+        description: "Re-capture the screen",
+    },
+    Binding {
+        keysym: Keysym::i,
+        action: Action::CycleContrast,
+        key: "I",
+        description: "High-contrast colors (off, around the cursor, or per line)",
+    },
+    Binding {
+        keysym: Keysym::u,
+        action: Action::CycleUnit,
+        key: "U",
+        description: "Unit (logical, device pixels or rem)",
+    },
+    Binding {
+        keysym: Keysym::g,
+        action: Action::ToggleGridSnap,
+        key: "G",
+        description: "Snap rectangles to grid",
+    },
+    Binding {
+        keysym: Keysym::l,
+        action: Action::ToggleLinearBlend,
+        key: "L",
+        description: "Linear-light blending",
+    },
+    Binding {
+        keysym: Keysym::L,
+        action: Action::ToggleAntialias,
+        key: "Shift+L",
+        description: "Crisp, non-anti-aliased lines",
+    },
+    Binding {
+        keysym: Keysym::t,
+        action: Action::ToggleRuler,
+        key: "T",
+        description: "Ruler tick marks",
+    },
+    Binding {
+        keysym: Keysym::_0,
+        action: Action::ToggleRoundSizes,
+        key: "0",
+        description: "Round sizes to nearby multiples of 10 and 5",
+    },
+    Binding {
+        keysym: Keysym::p,
+        action: Action::CycleLabelCorner,
+        key: "P",
+        description: "Pin labels to a corner",
+    },
+    Binding {
+        keysym: Keysym::P,
+        action: Action::TogglePin,
+        key: "Shift+P",
+        description: "Distance moved from a point pinned at the cursor",
+    },
+    Binding {
+        keysym: Keysym::s,
+        action: Action::ToggleGaps,
+        key: "S",
+        description: "Spacing to neighbouring elements",
+    },
+    Binding {
+        keysym: Keysym::n,
+        action: Action::ToggleMargins,
+        key: "N",
+        description: "Margins to the screen edges",
+    },
+    Binding {
+        keysym: Keysym::k,
+        action: Action::ToggleBaselines,
+        key: "K",
+        description: "Text baselines, line and cap height",
+    },
+    Binding {
+        keysym: Keysym::f,
+        action: Action::ToggleScreenSize,
+        key: "F",
+        description: "Full screen resolution",
+    },
+    Binding {
+        keysym: Keysym::F,
+        action: Action::ToggleContentBounds,
+        key: "Shift+F",
+        description: "Content area inside the background",
+    },
+    Binding {
+        keysym: Keysym::plus,
+        action: Action::ToggleRunningTotal,
+        key: "+",
+        description: "Add up measured sizes",
+    },
+    Binding {
+        keysym: Keysym::minus,
+        action: Action::ResetRunningTotal,
+        key: "-",
+        description: "Reset the running total",
+    },
+    Binding {
+        keysym: Keysym::c,
+        action: Action::SaveCrop,
+        key: "C",
+        description: "Save the rectangle as a PNG",
+    },
+    Binding {
+        keysym: Keysym::e,
+        action: Action::ToggleEdgeMap,
+        key: "E",
+        description: "Edge map (debug)",
+    },
+    Binding {
+        keysym: Keysym::E,
+        action: Action::ReportEdges,
+        key: "Shift+E",
+        description: "Print all edges on the cursor's row and column as JSON (debug)",
+    },
+    Binding {
+        keysym: Keysym::b,
+        action: Action::ToggleScanBounds,
+        key: "B",
+        description: "Confine edge detection to the rectangle",
+    },
+    Binding {
+        keysym: Keysym::B,
+        action: Action::ToggleBorderBox,
+        key: "Shift+B",
+        description: "Outer and inner box of a bordered element",
+    },
+    Binding {
+        keysym: Keysym::T,
+        action: Action::ToggleRunWidth,
+        key: "Shift+T",
+        description: "Thickness of the thin element under the cursor",
+    },
+    Binding {
+        keysym: Keysym::m,
+        action: Action::ToggleSmoothing,
+        key: "M",
+        description: "Smooth edge jitter",
+    },
+    Binding {
+        keysym: Keysym::M,
+        action: Action::ToggleEdgeConfidence,
+        key: "Shift+M",
+        description: "Color end caps by edge confidence",
+    },
+    Binding {
+        keysym: Keysym::x,
+        action: Action::CycleCrosshair,
+        key: "X",
+        description: "Crosshair style",
+    },
+    Binding {
+        keysym: Keysym::w,
+        action: Action::ToggleWindowOrigin,
+        key: "W",
+        description: "Position relative to the focused window",
+    },
+    Binding {
+        keysym: Keysym::d,
+        action: Action::TogglePathMode,
+        key: "D",
+        description: "Drag draws a free-hand path",
+    },
+    Binding {
+        keysym: Keysym::D,
+        action: Action::CycleMode,
+        key: "Shift+D",
+        description: "Cycle edge detection and dragging, edges only, dragging only",
+    },
+    Binding {
+        keysym: Keysym::j,
+        action: Action::ToggleRadial,
+        key: "J",
+        description: "Radius and angle from a center set at the cursor",
+    },
+    Binding {
+        keysym: Keysym::a,
+        action: Action::ToggleExtensionLines,
+        key: "A",
+        description: "Extend edges across the screen for alignment",
+    },
+    Binding {
+        keysym: Keysym::Tab,
+        action: Action::NextElement,
+        key: "Tab",
+        description: "Measure the next element to the right",
+    },
+    Binding {
+        keysym: Keysym::ISO_Left_Tab,
+        action: Action::PreviousElement,
+        key: "Shift+Tab",
+        description: "Measure the next element to the left",
+    },
+    Binding {
+        keysym: Keysym::bracketright,
+        action: Action::OuterContainer,
+        key: "]",
+        description: "Measure the enclosing container",
+    },
+    Binding {
+        keysym: Keysym::bracketleft,
+        action: Action::InnerContainer,
+        key: "[",
+        description: "Measure back in towards the element",
+    },
+    Binding {
+        keysym: Keysym::v,
+        action: Action::CycleColorReadout,
+        key: "V",
+        description: "Color under the cursor (off, hex, RGB or HSL first)",
+    },
+    Binding {
+        keysym: Keysym::y,
+        action: Action::CopyColor,
+        key: "Y",
+        description: "Copy the color under the cursor in the shown format",
+    },
+    Binding {
+        keysym: Keysym::equal,
+        action: Action::EnterSize,
+        key: "=",
+        description: "Type a size (e.g. 120x80, Enter) to overlay at the cursor",
+    },
+    Binding {
+        keysym: Keysym::h,
+        action: Action::TogglePointerLock,
+        key: "H",
+        description: "Hold the measurement point still, ignoring the mouse",
+    },
+    Binding {
+        keysym: Keysym::period,
+        action: Action::TogglePrecision,
+        key: ".",
+        description: "Precision mode: lock the cursor and move 0.1px per mouse unit",
+    },
+    Binding {
+        keysym: Keysym::z,
+        action: Action::ToggleLoupe,
+        key: "Z",
+        description: "Loupe magnifying the pixels under the cursor (scroll to zoom)",
+    },
+    Binding {
+        keysym: Keysym::o,
+        action: Action::ToggleNativeOrientation,
+        key: "O",
+        description: "Report sizes in a rotated output's native panel orientation",
+    },
+    Binding {
+        keysym: Keysym::question,
+        action: Action::ToggleHelp,
+        key: "?",
+        description: "Show this help",
+    },
+    Binding {
+        keysym: Keysym::Escape,
+        action: Action::Quit,
+        key: "Esc",
+        description: "Exit, or cancel the drag",
+    },
+    Binding {
+        keysym: Keysym::q,
+        action: Action::Quit,
+        key: "Q",
+        description: "Exit",
+    },
+];
+
+/// Shortcuts held with Ctrl, taking precedence over `BINDINGS` while it is down
+pub const CTRL_BINDINGS: &[Binding] = &[
+    Binding {
+        keysym: Keysym::equal,
+        action: Action::GrowLabels,
+        key: "Ctrl+=",
+        description: "Larger label text",
+    },
+    Binding {
+        keysym: Keysym::minus,
+        action: Action::ShrinkLabels,
+        key: "Ctrl+-",
+        description: "Smaller label text",
+    },
+    Binding {
+        keysym: Keysym::c,
+        action: Action::CopyMeasurement,
+        key: "Ctrl+C",
+        description: "Copy the measurement as WxH+X+Y",
+    },
+    Binding {
+        keysym: Keysym::C,
+        action: Action::CopyImage,
+        key: "Ctrl+Shift+C",
+        description: "Copy the annotated rectangle, or screen, as an image",
+    },
+];
+
+/// Pointer bindings listed in the help panel after the keyboard shortcuts
+pub const OTHER_BINDINGS: &[(&str, &str)] = &[
+    ("Drag", "Measure a rectangle"),
+    ("Shift+Drag", "Keep the previous rectangle"),
+    ("Alt+Drag", "Grow the rectangle from its center"),
+    ("Right-click", "Cancel the drag"),
+    ("Drag its edge", "Resize the rectangle"),
+    ("Arrows", "Move the rectangle 1px, Shift for 10px"),
+];
+
+/// Unit step `(dx, dy)` an arrow key moves the drawn rectangle by
+pub fn arrow_direction(keysym: Keysym) -> Option<(i32, i32)> {
+    match keysym {
+        Keysym::Left => Some((-1, 0)),
+        Keysym::Right => Some((1, 0)),
+        Keysym::Up => Some((0, -1)),
+        Keysym::Down => Some((0, 1)),
+        _ => None,
+    }
+}
+
+/// Action for a key press. Keys bound with `--bind` come first and take their action's
+/// default keys away; keys bound to nothing return `None`.
+pub fn action_for(keysym: Keysym, ctrl: bool, keymap: &[KeyBind]) -> Option<Action> {
+    if !ctrl && let Some(bind) = keymap.iter().find(|bind| bind.keysym == keysym) {
+        return Some(bind.action);
+    }
+    ctrl.then_some(CTRL_BINDINGS)
+        .into_iter()
+        .flatten()
+        .chain(BINDINGS)
+        .filter(|b| keymap.iter().all(|bind| bind.action != b.action))
+        .find(|b| b.keysym == keysym)
+        .map(|b| b.action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_names_are_kebab_case() {
+        assert_eq!(Action::Quit.name(), "quit");
+        assert_eq!(Action::CopyColor.name(), "copy-color");
+        assert_eq!("toggle-grid-snap".parse(), Ok(Action::ToggleGridSnap));
+        assert_eq!("grid-snap".parse::<Action>(), Err(()));
+    }
+
+    #[test]
+    fn parses_bind_by_keysym_name_or_character() {
+        let bind: KeyBind = "quit=x".parse().unwrap();
+        assert_eq!((bind.action, bind.keysym), (Action::Quit, Keysym::x));
+        let bind: KeyBind = "recapture=F5".parse().unwrap();
+        assert_eq!(bind.keysym, Keysym::F5);
+        let bind: KeyBind = "copy-color=#".parse().unwrap();
+        assert_eq!(bind.keysym, Keysym::numbersign);
+        assert!("quit=NotAKey".parse::<KeyBind>().is_err());
+        assert!("quit".parse::<KeyBind>().is_err());
+    }
+
+    #[test]
+    fn rebinding_moves_the_action_off_its_default_key() {
+        let keymap = ["quit=x".parse().unwrap()];
+        assert_eq!(action_for(Keysym::x, false, &keymap), Some(Action::Quit));
+        assert_eq!(action_for(Keysym::Escape, false, &keymap), None);
+        assert_eq!(action_for(Keysym::q, false, &keymap), None);
+        assert_eq!(action_for(Keysym::y, false, &[]), Some(Action::CopyColor));
+        assert_eq!(action_for(Keysym::F5, false, &[]), None);
+    }
+}
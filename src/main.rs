@@ -1,25 +1,202 @@
 mod capture;
+mod clipboard;
+mod config;
+mod daemon;
+mod diff;
 mod edge_detection;
+mod font;
+mod keybinds;
+mod notify;
+mod output_picker;
+mod shell;
+mod state;
 mod ui;
 mod wayland_handlers;
 
-use capture::{capture_screen, get_focused_monitor_info};
+use capture::{
+    Screenshot, capture_screen, get_focused_monitor_info, get_monitor_scale, get_monitors,
+    load_image,
+};
+use config::Config;
+use edge_detection::find_edges;
+use output_picker::{Candidate, pick_output};
+use smithay_client_toolkit::reexports::{
+    calloop::{
+        EventLoop,
+        timer::{TimeoutAction, Timer},
+    },
+    calloop_wayland_source::WaylandSource,
+};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use wayland_client::Connection;
 use wayland_handlers::WaylandApp;
 
+// Spacing in physical pixels of the cursor positions `bench` measures from
+const BENCH_GRID_STEP: usize = 32;
+
 fn main() {
+    let config = match Config::from_args() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("hypruler: {}", e);
+            std::process::exit(2);
+        }
+    };
+
     let conn = Connection::connect_to_env().expect("Failed to connect to Wayland");
 
     let monitor_info = get_focused_monitor_info();
-    let target_output_name = monitor_info.as_ref().map(|(name, _)| name.clone());
-    let transform = monitor_info.map(|(_, t)| t).unwrap_or(0);
+    let mut target_output_name = config
+        .output
+        .clone()
+        .or_else(|| monitor_info.as_ref().map(|(name, _)| name.clone()));
+    let mut transform = match &config.output {
+        // An image is already upright
+        _ if config.image.is_some() => 0,
+        Some(output) => get_monitors()
+            .into_iter()
+            .find(|(name, _)| name == output)
+            .map_or(0, |(_, t)| t),
+        None => monitor_info.map(|(_, t)| t).unwrap_or(0),
+    };
 
-    let screenshot = match capture_screen(&conn, target_output_name.as_deref(), transform) {
+    let capture = |name: Option<&str>, transform, region| match capture_screen(
+        &conn,
+        name,
+        transform,
+        region,
+        config.linear_luminance,
+    ) {
         Ok(s) => s,
-        Err(_) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("hypruler: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Only the overlay is interactive, and an output given by name needs no picking
+    let interactive = !config.bench && !config.daemon && config.dump_capture.is_none();
+    let monitors =
+        if config.pick_output && interactive && config.output.is_none() && config.image.is_none() {
+            get_monitors()
+        } else {
+            Vec::new()
+        };
+    let screenshot = if let Some(path) = &config.image {
+        match load_image(path, config.linear_luminance) {
+            Ok(screenshot) => screenshot,
+            Err(e) => {
+                eprintln!("hypruler: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if monitors.len() > 1 {
+        // Let the user click the output to measure on, then carry on with its capture
+        let candidates = monitors
+            .into_iter()
+            .map(|(name, transform)| Candidate {
+                screenshot: capture(Some(&name), transform, None),
+                name,
+                transform,
+            })
+            .collect();
+        let Some(picked) = pick_output(&conn, candidates) else {
+            return;
+        };
+        target_output_name = Some(picked.name);
+        transform = picked.transform;
+        match config.region {
+            Some(region) => capture(target_output_name.as_deref(), transform, Some(region)),
+            None => picked.screenshot,
+        }
+    } else {
+        capture(target_output_name.as_deref(), transform, config.region)
+    };
+    // Place the overlay on exactly the output that was captured, even when it was picked as
+    // the first available one rather than by name
+    if screenshot.output_name.is_some() {
+        target_output_name = screenshot.output_name.clone();
+    }
+
+    if let Some(path) = &config.dump_capture {
+        match dump_capture(&screenshot, path) {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => {
+                eprintln!("hypruler: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Scale of the captured output, to convert logical options like `--min-size`; an image
+    // is measured 1:1
+    let capture_scale = || match &config.image {
+        Some(_) => 1.0,
+        None => get_monitor_scale(target_output_name.as_deref()).unwrap_or(1.0),
+    };
+
+    if config.bench {
+        bench(&screenshot, &config, capture_scale());
+        return;
+    }
+
+    if config.daemon {
+        let result = daemon::run(
+            &conn,
+            screenshot,
+            &config,
+            target_output_name.as_deref(),
+            transform,
+            capture_scale(),
+        );
+        if let Err(e) = result {
+            eprintln!("hypruler: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let reference = match &config.compare {
+        Some(path) => match diff::load_reference(path) {
+            Ok(reference)
+                if (reference.width(), reference.height())
+                    == (screenshot.width, screenshot.height) =>
+            {
+                Some(reference)
+            }
+            Ok(reference) => {
+                eprintln!(
+                    "hypruler: {} is {}x{} but the capture is {}x{}",
+                    path.display(),
+                    reference.width(),
+                    reference.height(),
+                    screenshot.width,
+                    screenshot.height
+                );
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("hypruler: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
     };
 
-    let (mut app, mut event_queue) = WaylandApp::new(&conn, screenshot, target_output_name);
+    let timeout = config.timeout;
+    let live_fps = config.live_fps;
+    let print_geometry = config.print_geometry;
+    let notify = config.notify;
+    let (mut app, mut event_queue) = WaylandApp::new(
+        &conn,
+        config,
+        screenshot,
+        reference,
+        target_output_name,
+        transform,
+    );
     let qh = event_queue.handle();
 
     // Roundtrip to ensure outputs are populated before creating surface
@@ -27,7 +204,108 @@ fn main() {
 
     app.create_surface(&qh);
 
+    // Drive Wayland events through calloop so timers can run alongside them
+    let mut event_loop: EventLoop<WaylandApp> =
+        EventLoop::try_new().expect("Failed to create event loop");
+    WaylandSource::new(conn.clone(), event_queue)
+        .insert(event_loop.handle())
+        .expect("Failed to insert Wayland source");
+
+    if let Some(timeout) = timeout {
+        // Exit after `timeout` without input, re-arming for the remaining time otherwise
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(timeout), move |_, _, app| {
+                let idle = app.idle_time();
+                if idle >= timeout {
+                    app.request_exit();
+                    TimeoutAction::Drop
+                } else {
+                    TimeoutAction::ToDuration(timeout - idle)
+                }
+            })
+            .expect("Failed to insert timeout timer");
+    }
+
+    if let Some(fps) = live_fps {
+        // Refresh the background on a fixed interval so animated content can be measured
+        let interval = Duration::from_secs(1) / fps;
+        let conn = conn.clone();
+        let qh = qh.clone();
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(interval), move |_, _, app| {
+                app.recapture(&conn, &qh);
+                TimeoutAction::ToDuration(interval)
+            })
+            .expect("Failed to insert live capture timer");
+    }
+
     while !app.should_exit() {
-        event_queue.blocking_dispatch(&mut app).unwrap();
+        event_loop.dispatch(None, &mut app).unwrap();
+    }
+
+    if let Err(e) = app.state().save() {
+        eprintln!("hypruler: failed to save state: {}", e);
+    }
+
+    if print_geometry {
+        println!("{}", app.geometry());
+    }
+    if notify {
+        app.notify();
     }
 }
+
+/// Write the whole capture as a PNG next to `path`, with the size appended to the file name
+fn dump_capture(screenshot: &Screenshot, path: &Path) -> Result<PathBuf, String> {
+    let path = if path.is_dir() {
+        path.join("hypruler-capture")
+    } else {
+        path.to_path_buf()
+    };
+    let stem = path
+        .file_stem()
+        .map_or("hypruler-capture".into(), |s| s.to_string_lossy());
+    let path = path.with_file_name(format!(
+        "{}-{}x{}.png",
+        stem, screenshot.width, screenshot.height
+    ));
+    let png = screenshot.crop_png(0, 0, screenshot.width - 1, screenshot.height - 1)?;
+    std::fs::write(&path, png).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Run `find_edges` at every point of a grid over the capture and print per-call timings
+fn bench(screenshot: &Screenshot, config: &Config, scale: f64) {
+    let options = config.scan_options(scale);
+    let mut timings: Vec<Duration> = (0..screenshot.height)
+        .step_by(BENCH_GRID_STEP)
+        .flat_map(|y| {
+            (0..screenshot.width)
+                .step_by(BENCH_GRID_STEP)
+                .map(move |x| (x, y))
+        })
+        .map(|(x, y)| {
+            let start = Instant::now();
+            std::hint::black_box(find_edges(screenshot, x, y, None, &options));
+            start.elapsed()
+        })
+        .collect();
+    timings.sort_unstable();
+
+    let total: Duration = timings.iter().sum();
+    println!(
+        "find_edges on {}x{}: {} calls in {:.2?}",
+        screenshot.width,
+        screenshot.height,
+        timings.len(),
+        total
+    );
+    println!(
+        "min {:.2?}, median {:.2?}, max {:.2?}",
+        timings[0],
+        timings[timings.len() / 2],
+        timings[timings.len() - 1]
+    );
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Show a desktop notification through `notify-send`, which talks to the
+/// `org.freedesktop.Notifications` D-Bus service. `image` is shown as the icon where the
+/// notification daemon supports it.
+pub fn notify(summary: &str, body: &str, image: Option<&Path>) -> Result<(), String> {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name", "hypruler"]);
+    if let Some(image) = image {
+        command.arg("--icon").arg(image);
+    }
+    let status = command
+        .arg(summary)
+        .arg(body)
+        .status()
+        .map_err(|e| format!("Failed to run notify-send: {}", e))?;
+    if !status.success() {
+        return Err(format!("notify-send exited with {}", status));
+    }
+    Ok(())
+}
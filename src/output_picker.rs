@@ -0,0 +1,453 @@
+use crate::capture::Screenshot;
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState},
+    delegate_compositor, delegate_keyboard, delegate_layer, delegate_output, delegate_pointer,
+    delegate_registry, delegate_seat, delegate_shm,
+    output::{OutputHandler, OutputState},
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    seat::{
+        Capability, SeatHandler, SeatState,
+        keyboard::{KeyEvent, KeyboardHandler, Keysym, Modifiers, RawModifiers},
+        pointer::{PointerEvent, PointerEventKind, PointerHandler},
+    },
+    shell::{
+        WaylandSurface,
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+    },
+    shm::{
+        Shm, ShmHandler,
+        slot::{Buffer, SlotPool},
+    },
+};
+use wayland_client::{
+    Connection, Dispatch, Proxy, QueueHandle,
+    globals::registry_queue_init,
+    protocol::{wl_keyboard, wl_output, wl_pointer, wl_seat, wl_shm, wl_surface},
+};
+use wayland_protocols::wp::viewporter::client::{
+    wp_viewport::WpViewport, wp_viewporter::WpViewporter,
+};
+
+// Brightness of the outputs not under the pointer
+const DIM_FACTOR: u32 = 128;
+
+/// An output that can be picked, with its capture
+pub struct Candidate {
+    pub name: String,
+    pub transform: u32,
+    pub screenshot: Screenshot,
+}
+
+/// Layer surface showing one candidate's capture
+struct PickerSurface {
+    layer_surface: LayerSurface,
+    viewport: Option<WpViewport>,
+    pool: Option<SlotPool>,
+    // Kept alive until the compositor is done with it
+    buffer: Option<Buffer>,
+    width: u32,
+    height: u32,
+}
+
+struct OutputPicker {
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    output_state: OutputState,
+    shm: Shm,
+    candidates: Vec<Candidate>,
+    surfaces: Vec<PickerSurface>,
+    // Index of the surface under the pointer
+    hovered: Option<usize>,
+    picked: Option<usize>,
+    exit: bool,
+}
+
+/// Show every candidate's capture dimmed on its own output, highlighting the one under the
+/// pointer, and return the one clicked. Returns `None` if the user cancels with Escape.
+/// Without layer shell the picker can't be shown, so the first (focused) candidate is taken.
+pub fn pick_output(conn: &Connection, candidates: Vec<Candidate>) -> Option<Candidate> {
+    let (globals, mut event_queue) = registry_queue_init(conn).expect("Failed to init registry");
+    let qh = event_queue.handle();
+
+    let compositor_state =
+        CompositorState::bind(&globals, &qh).expect("wl_compositor not available");
+    let Ok(layer_shell) = LayerShell::bind(&globals, &qh) else {
+        eprintln!("hypruler: layer shell not available, measuring the focused output");
+        return candidates.into_iter().next();
+    };
+    let viewporter: Option<WpViewporter> = globals.bind(&qh, 1..=1, ()).ok();
+
+    let mut picker = OutputPicker {
+        registry_state: RegistryState::new(&globals),
+        seat_state: SeatState::new(&globals, &qh),
+        output_state: OutputState::new(&globals, &qh),
+        shm: Shm::bind(&globals, &qh).expect("wl_shm not available"),
+        candidates,
+        surfaces: Vec::new(),
+        hovered: None,
+        picked: None,
+        exit: false,
+    };
+
+    // Roundtrip so outputs are known before placing a surface on each
+    event_queue.roundtrip(&mut picker).ok()?;
+
+    for (i, candidate) in picker.candidates.iter().enumerate() {
+        let output = picker.output_state.outputs().find(|o| {
+            picker
+                .output_state
+                .info(o)
+                .is_some_and(|info| info.name.as_deref() == Some(candidate.name.as_str()))
+        });
+        let surface = compositor_state.create_surface(&qh);
+        let viewport = viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(&surface, &qh, ()));
+        let layer_surface = layer_shell.create_layer_surface(
+            &qh,
+            surface,
+            Layer::Overlay,
+            Some("hypruler"),
+            output.as_ref(),
+        );
+        layer_surface.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
+        layer_surface.set_exclusive_zone(-1);
+        // Only one surface can hold the keyboard; the first is the focused output
+        layer_surface.set_keyboard_interactivity(if i == 0 {
+            KeyboardInteractivity::Exclusive
+        } else {
+            KeyboardInteractivity::None
+        });
+        layer_surface.commit();
+        picker.surfaces.push(PickerSurface {
+            layer_surface,
+            viewport,
+            pool: None,
+            buffer: None,
+            width: 0,
+            height: 0,
+        });
+    }
+
+    while !picker.exit {
+        event_queue.blocking_dispatch(&mut picker).ok()?;
+    }
+
+    // Take the surfaces down before the overlay replaces them
+    picker.surfaces.clear();
+    event_queue.roundtrip(&mut picker).ok()?;
+
+    let picked = picker.picked?;
+    Some(picker.candidates.swap_remove(picked))
+}
+
+impl OutputPicker {
+    fn surface_index(&self, surface: &wl_surface::WlSurface) -> Option<usize> {
+        self.surfaces
+            .iter()
+            .position(|s| s.layer_surface.wl_surface() == surface)
+    }
+
+    /// Draw surface `index`: its capture, dimmed unless the pointer is over it
+    fn draw(&mut self, index: usize) {
+        let dimmed = self.hovered != Some(index);
+        let screenshot = &self.candidates[index].screenshot;
+        let surface = &mut self.surfaces[index];
+        if surface.width == 0 || surface.height == 0 {
+            return;
+        }
+
+        let (width, height) = (screenshot.width, screenshot.height);
+        let stride = width as usize * 4;
+        let pool = surface.pool.get_or_insert_with(|| {
+            SlotPool::new(stride * height as usize, &self.shm).expect("Failed to create pool")
+        });
+        let Ok((buffer, canvas)) = pool.create_buffer(
+            width as i32,
+            height as i32,
+            stride as i32,
+            wl_shm::Format::Argb8888,
+        ) else {
+            return;
+        };
+
+        for (y, row) in canvas.chunks_exact_mut(stride).enumerate() {
+            screenshot.copy_row(y as u32, 0, width - 1, row);
+            if dimmed {
+                for pixel in row.chunks_exact_mut(4) {
+                    for channel in &mut pixel[..3] {
+                        *channel = (*channel as u32 * DIM_FACTOR / 255) as u8;
+                    }
+                }
+            }
+        }
+
+        let wl_surface = surface.layer_surface.wl_surface();
+        match &surface.viewport {
+            Some(viewport) => viewport.set_destination(surface.width as i32, surface.height as i32),
+            None => wl_surface.set_buffer_scale((width / surface.width).max(1) as i32),
+        }
+        buffer
+            .attach_to(wl_surface)
+            .expect("Failed to attach buffer");
+        wl_surface.damage_buffer(0, 0, width as i32, height as i32);
+        wl_surface.commit();
+        surface.buffer = Some(buffer);
+    }
+}
+
+impl CompositorHandler for OutputPicker {
+    fn scale_factor_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: i32,
+    ) {
+    }
+    fn transform_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: wl_output::Transform,
+    ) {
+    }
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+    fn surface_enter(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+    fn surface_leave(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for OutputPicker {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+}
+
+impl LayerShellHandler for OutputPicker {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
+        self.exit = true;
+    }
+
+    fn configure(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        layer_surface: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _: u32,
+    ) {
+        let Some(index) = self.surface_index(layer_surface.wl_surface()) else {
+            return;
+        };
+        let surface = &mut self.surfaces[index];
+        surface.width = configure.new_size.0;
+        surface.height = configure.new_size.1;
+        self.draw(index);
+    }
+}
+
+impl SeatHandler for OutputPicker {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+
+    fn new_capability(
+        &mut self,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: wl_seat::WlSeat,
+        capability: Capability,
+    ) {
+        if capability == Capability::Pointer {
+            let _ = self.seat_state.get_pointer(qh, &seat);
+        }
+        if capability == Capability::Keyboard {
+            let _ = self.seat_state.get_keyboard(qh, &seat, None);
+        }
+    }
+
+    fn remove_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: wl_seat::WlSeat,
+        _: Capability,
+    ) {
+    }
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+}
+
+impl KeyboardHandler for OutputPicker {
+    fn enter(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: &wl_surface::WlSurface,
+        _: u32,
+        _: &[u32],
+        _: &[Keysym],
+    ) {
+    }
+    fn leave(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: &wl_surface::WlSurface,
+        _: u32,
+    ) {
+    }
+
+    fn press_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        event: KeyEvent,
+    ) {
+        if event.keysym == Keysym::Escape {
+            self.exit = true;
+        }
+    }
+
+    fn release_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: KeyEvent,
+    ) {
+    }
+    fn update_modifiers(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: Modifiers,
+        _: RawModifiers,
+        _: u32,
+    ) {
+    }
+    fn repeat_key(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_keyboard::WlKeyboard,
+        _: u32,
+        _: KeyEvent,
+    ) {
+    }
+}
+
+impl PointerHandler for OutputPicker {
+    fn pointer_frame(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_pointer::WlPointer,
+        events: &[PointerEvent],
+    ) {
+        for event in events {
+            let index = self.surface_index(&event.surface);
+            match event.kind {
+                PointerEventKind::Enter { .. } => {
+                    let Some(index) = index else {
+                        continue;
+                    };
+                    if let Some(previous) = self.hovered.replace(index)
+                        && previous != index
+                    {
+                        self.draw(previous);
+                    }
+                    self.draw(index);
+                }
+                PointerEventKind::Leave { .. } if self.hovered == index => {
+                    self.hovered = None;
+                    if let Some(i) = index {
+                        self.draw(i);
+                    }
+                }
+                PointerEventKind::Press { .. } if index.is_some() => {
+                    self.picked = index;
+                    self.exit = true;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl ShmHandler for OutputPicker {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for OutputPicker {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+    registry_handlers![OutputState, SeatState];
+}
+
+delegate_compositor!(OutputPicker);
+delegate_output!(OutputPicker);
+delegate_shm!(OutputPicker);
+delegate_seat!(OutputPicker);
+delegate_keyboard!(OutputPicker);
+delegate_pointer!(OutputPicker);
+delegate_layer!(OutputPicker);
+delegate_registry!(OutputPicker);
+
+impl Dispatch<WpViewporter, ()> for OutputPicker {
+    fn event(
+        _: &mut Self,
+        _: &WpViewporter,
+        _: <WpViewporter as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WpViewport, ()> for OutputPicker {
+    fn event(
+        _: &mut Self,
+        _: &WpViewport,
+        _: <WpViewport as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
@@ -0,0 +1,91 @@
+use crate::wayland_handlers::WaylandApp;
+use smithay_client_toolkit::shell::{
+    WaylandSurface,
+    wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerShell, LayerSurface},
+    xdg::{
+        XdgShell,
+        window::{Window, WindowDecorations},
+    },
+};
+use wayland_client::{
+    QueueHandle,
+    globals::GlobalList,
+    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+};
+
+/// A shell that can cover an output with the overlay
+pub trait OverlayShell {
+    /// Give `surface` a role covering `output`, or one the compositor picks. The overlay
+    /// is drawn once the compositor configures it after the first commit.
+    fn create_overlay(
+        &self,
+        qh: &QueueHandle<WaylandApp>,
+        surface: WlSurface,
+        output: Option<&WlOutput>,
+    ) -> Box<dyn OverlaySurface>;
+}
+
+/// The overlay's shell surface, which keeps it mapped for as long as it is alive
+pub trait OverlaySurface {
+    fn wl_surface(&self) -> &WlSurface;
+
+    fn commit(&self) {
+        self.wl_surface().commit();
+    }
+}
+
+/// Bind wlr-layer-shell, or where the compositor lacks it (e.g. GNOME) fall back to a
+/// fullscreen xdg toplevel. `None` when neither is available.
+pub fn bind(globals: &GlobalList, qh: &QueueHandle<WaylandApp>) -> Option<Box<dyn OverlayShell>> {
+    if let Ok(layer_shell) = LayerShell::bind(globals, qh) {
+        return Some(Box::new(layer_shell));
+    }
+    let xdg_shell = XdgShell::bind(globals, qh).ok()?;
+    eprintln!("hypruler: layer shell not available, showing the overlay as a fullscreen window");
+    Some(Box::new(xdg_shell))
+}
+
+impl OverlayShell for LayerShell {
+    fn create_overlay(
+        &self,
+        qh: &QueueHandle<WaylandApp>,
+        surface: WlSurface,
+        output: Option<&WlOutput>,
+    ) -> Box<dyn OverlaySurface> {
+        let layer_surface =
+            self.create_layer_surface(qh, surface, Layer::Overlay, Some("hypruler"), output);
+        layer_surface.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
+        layer_surface.set_exclusive_zone(-1);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+        Box::new(layer_surface)
+    }
+}
+
+impl OverlayShell for XdgShell {
+    fn create_overlay(
+        &self,
+        qh: &QueueHandle<WaylandApp>,
+        surface: WlSurface,
+        output: Option<&WlOutput>,
+    ) -> Box<dyn OverlaySurface> {
+        // A toplevel can't stay above other windows, but fullscreen gets close and the
+        // compositor gives it keyboard focus
+        let window = self.create_window(surface, WindowDecorations::None, qh);
+        window.set_title("hypruler");
+        window.set_app_id("hypruler");
+        window.set_fullscreen(output);
+        Box::new(window)
+    }
+}
+
+impl OverlaySurface for LayerSurface {
+    fn wl_surface(&self) -> &WlSurface {
+        WaylandSurface::wl_surface(self)
+    }
+}
+
+impl OverlaySurface for Window {
+    fn wl_surface(&self) -> &WlSurface {
+        WaylandSurface::wl_surface(self)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::config::Mode;
+use crate::ui::{ColorFormat, Contrast, Corner, CrosshairStyle, Unit};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Toggles remembered between runs, so a tuned setup survives relaunching
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub contrast: Contrast,
+    pub unit: Unit,
+    pub grid_snap: bool,
+    pub linear_blend: bool,
+    /// Draw lines and shapes without anti-aliasing
+    pub crisp_lines: bool,
+    pub ruler: bool,
+    pub round_sizes: bool,
+    pub label_corner: Option<Corner>,
+    pub show_gaps: bool,
+    pub show_margins: bool,
+    pub show_baselines: bool,
+    pub show_border_box: bool,
+    pub run_width: bool,
+    pub smoothing: bool,
+    pub edge_confidence: bool,
+    pub crosshair: CrosshairStyle,
+    pub mode: Mode,
+    pub extension_lines: bool,
+    pub color_readout: Option<ColorFormat>,
+    pub loupe: bool,
+    pub native_orientation: bool,
+    /// Label text size, when changed from the default
+    pub font_size: Option<u32>,
+}
+
+/// `$XDG_STATE_HOME/hypruler/state.json`, falling back to `~/.local/state`
+fn state_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".local/state")))?;
+    Some(dir.join("hypruler").join("state.json"))
+}
+
+impl State {
+    /// Load the saved state, or defaults if there is none or it can't be read
+    pub fn load() -> Self {
+        state_path()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = state_path().ok_or("No state directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
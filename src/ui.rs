@@ -1,15 +1,67 @@
-use crate::edge_detection::Edges;
+use crate::capture::{Screenshot, linear_to_srgb, srgb_to_linear};
+use crate::config::Size;
+use crate::diff::Diff;
+use crate::edge_detection::{BorderBox, EdgeStrength, Edges, Gaps, ScanBounds, TextLines};
+use crate::font::Font;
+use serde::{Deserialize, Serialize};
 use tiny_skia::{
-    Color, FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Stroke, Transform,
+    Color, ColorU8, FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PremultipliedColorU8,
+    Rect, Stroke, StrokeDash, Transform,
 };
 
 const LINE_WIDTH: f32 = 2.0;
 const END_CAP_SIZE: f32 = 16.0;
 const CROSSHAIR_SIZE: f32 = 15.0;
-const FONT_SIZE: f32 = 24.0;
+pub const DEFAULT_FONT_SIZE: u32 = 24;
+// Label padding and corner radius at the default font size, scaled along with it
 const LABEL_PADDING: (f32, f32) = (12.0, 6.0);
 const LABEL_RADIUS: f32 = 6.0;
 const LABEL_OFFSET: (f32, f32) = (95.0, 40.0);
+// Ruler ticks every N logical pixels, with a longer tick every major step
+const RULER_TICK_SPACING: u32 = 10;
+const RULER_MAJOR_SPACING: u32 = 100;
+const RULER_TICK_SIZE: f32 = 4.0;
+const RULER_MAJOR_TICK_SIZE: f32 = 10.0;
+// Largest difference in logical pixels, per side, still counted as matching `--target`
+const TARGET_TOLERANCE: f64 = 1.0;
+// Round-number steps, preferred first, and the largest difference rounded across to reach one
+const ROUND_STEPS: [(u32, u32); 2] = [(10, 2), (5, 1)];
+pub const MM_PER_INCH: f64 = 25.4;
+// Points sampled along a line to pick its color against the content underneath
+const CONTRAST_LINE_SAMPLES: u32 = 32;
+const DASH_PATTERN: [f32; 2] = [8.0, 6.0];
+
+const EXTENSION_LINE_OPACITY: f32 = 0.35;
+const DIFF_OPACITY: f32 = 0.4;
+
+// Distance of corner-pinned labels from the screen edges
+const CORNER_MARGIN: f32 = 16.0;
+// Side of the loupe in physical pixels, before rounding to whole magnified pixels
+const LOUPE_SIZE: u32 = 160;
+const LOUPE_BORDER: f32 = 4.0;
+// Magnification from which the loupe outlines every device pixel
+const LOUPE_GRID_MIN_ZOOM: u32 = 4;
+const LOUPE_GRID_OPACITY: f32 = 0.25;
+const HELP_PADDING: f32 = 24.0;
+// Stroked digits used when no font could be loaded, relative to the font size: glyph height
+// and width, advance per character and stroke width
+const FALLBACK_GLYPH_SIZE: (f32, f32) = (0.7, 0.4);
+const FALLBACK_ADVANCE: f32 = 0.6;
+const FALLBACK_STROKE: f32 = 0.08;
+// Seven-segment masks for 0-9, bits a (top) to g (middle) clockwise from the top
+const SEGMENT_DIGITS: [u8; 10] = [
+    0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111,
+    0b1111111, 0b1101111,
+];
+// Help panel line height relative to the font size
+const HELP_LINE_SPACING: f32 = 1.5;
+const HELP_COLUMN_GAP: f32 = 32.0;
+
+// Edges of different rectangles closer than this (in logical pixels) but not equal are flagged
+const MISALIGN_TOLERANCE: f64 = 3.0;
+
+// Largest reduced ratio term still shown as `a:b` rather than a decimal
+const MAX_RATIO_TERM: u32 = 32;
 
 // How close to screen edges before flipping label position:
 const EDGE_THRESHOLD_X: f32 = 200.0;
@@ -29,16 +81,508 @@ fn get_label_position(cx: f32, cy: f32, screen_w: u32, screen_h: u32) -> (f32, f
     (x, y)
 }
 
-fn line_color() -> Color {
-    Color::from_rgba8(231, 76, 60, 255)
+/// Colors used for the overlay annotations
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub line: Color,
+    pub fill: Color,
+    pub label_bg: Color,
+    pub label_text: ColorU8,
+    pub warning: Color,
+    /// Secondary annotations such as gaps between elements
+    pub secondary: Color,
+    /// Lines of a measurement that matches the target size
+    pub matched: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            line: Color::from_rgba8(231, 76, 60, 255),
+            fill: Color::from_rgba8(231, 76, 60, 60),
+            label_bg: Color::from_rgba8(40, 40, 40, 230),
+            label_text: ColorU8::from_rgba(255, 255, 255, 255),
+            warning: Color::from_rgba8(241, 196, 15, 255),
+            secondary: Color::from_rgba8(52, 152, 219, 255),
+            matched: Color::from_rgba8(46, 204, 113, 255),
+        }
+    }
+}
+
+impl Palette {
+    /// High-contrast palette picked to stand out against the given background luminance
+    pub fn high_contrast(background_luminance: u8) -> Self {
+        if background_luminance >= 128 {
+            // Light background: black lines, black label with white text
+            Self {
+                line: Color::from_rgba8(0, 0, 0, 255),
+                fill: Color::from_rgba8(0, 0, 0, 60),
+                label_bg: Color::from_rgba8(0, 0, 0, 240),
+                label_text: ColorU8::from_rgba(255, 255, 255, 255),
+                warning: Color::from_rgba8(255, 0, 255, 255),
+                secondary: Color::from_rgba8(0, 0, 255, 255),
+                matched: Color::from_rgba8(0, 150, 0, 255),
+            }
+        } else {
+            // Dark background: cyan lines, white label with black text
+            Self {
+                line: Color::from_rgba8(0, 255, 255, 255),
+                fill: Color::from_rgba8(0, 255, 255, 60),
+                label_bg: Color::from_rgba8(255, 255, 255, 240),
+                label_text: ColorU8::from_rgba(0, 0, 0, 255),
+                warning: Color::from_rgba8(255, 255, 0, 255),
+                secondary: Color::from_rgba8(0, 255, 0, 255),
+                matched: Color::from_rgba8(0, 255, 128, 255),
+            }
+        }
+    }
+}
+
+/// Unit that measurements are reported in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Unit {
+    /// Logical (CSS) pixels: physical pixels divided by the output scale
+    #[default]
+    Logical,
+    /// Device (physical) pixels as captured
+    Device,
+    /// CSS rem: logical pixels divided by the root font size, with the pixel value alongside
+    Rem,
+}
+
+impl Unit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Logical => "logical",
+            Unit::Device => "device",
+            Unit::Rem => "rem",
+        }
+    }
+}
+
+/// Screen corner that labels can be pinned to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+impl Corner {
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "top-left",
+            Corner::TopRight => "top-right",
+            Corner::BottomRight => "bottom-right",
+            Corner::BottomLeft => "bottom-left",
+        }
+    }
+}
+
+/// How the cursor position is marked
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrosshairStyle {
+    /// Short cross centered on the cursor
+    #[default]
+    Cross,
+    /// A single pixel
+    Dot,
+    /// Thin horizontal and vertical lines across the whole screen
+    Lines,
+}
+
+impl CrosshairStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            CrosshairStyle::Cross => "cross",
+            CrosshairStyle::Dot => "dot",
+            CrosshairStyle::Lines => "lines",
+        }
+    }
+}
+
+impl std::str::FromStr for CrosshairStyle {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        [
+            CrosshairStyle::Cross,
+            CrosshairStyle::Dot,
+            CrosshairStyle::Lines,
+        ]
+        .into_iter()
+        .find(|style| style.name() == s)
+        .ok_or(())
+    }
+}
+
+/// Screen edge the status bar strip runs along
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarPosition {
+    Top,
+    Bottom,
+}
+
+impl BarPosition {
+    pub fn name(self) -> &'static str {
+        match self {
+            BarPosition::Top => "top",
+            BarPosition::Bottom => "bottom",
+        }
+    }
+}
+
+impl std::str::FromStr for BarPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        [BarPosition::Top, BarPosition::Bottom]
+            .into_iter()
+            .find(|position| position.name() == s)
+            .ok_or(())
+    }
+}
+
+/// Real-world unit sizes are shown in alongside pixels, given the monitor's density
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RealUnit {
+    Millimeters,
+    Inches,
+}
+
+impl RealUnit {
+    pub fn name(self) -> &'static str {
+        match self {
+            RealUnit::Millimeters => "mm",
+            RealUnit::Inches => "in",
+        }
+    }
+}
+
+impl std::str::FromStr for RealUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        [RealUnit::Millimeters, RealUnit::Inches]
+            .into_iter()
+            .find(|unit| unit.name() == s)
+            .ok_or(())
+    }
+}
+
+/// How the overlay colors follow the content underneath
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Contrast {
+    /// The default red palette
+    #[default]
+    Off,
+    /// A high-contrast palette picked against the content around the cursor
+    Cursor,
+    /// As `Cursor`, with each measurement line also picked against the content it crosses
+    PerLine,
+}
+
+impl Contrast {
+    pub fn name(self) -> &'static str {
+        match self {
+            Contrast::Off => "off",
+            Contrast::Cursor => "cursor",
+            Contrast::PerLine => "per-line",
+        }
+    }
+}
+
+/// Notation for the color under the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorFormat {
+    /// `#E74C3C`
+    #[default]
+    Hex,
+    /// `RGB(231,76,60)`
+    Rgb,
+    /// `HSL(6,78%,57%)`
+    Hsl,
+}
+
+impl ColorFormat {
+    pub const ALL: [ColorFormat; 3] = [ColorFormat::Hex, ColorFormat::Rgb, ColorFormat::Hsl];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorFormat::Hex => "hex",
+            ColorFormat::Rgb => "rgb",
+            ColorFormat::Hsl => "hsl",
+        }
+    }
+
+    pub fn format(self, [r, g, b]: [u8; 3]) -> String {
+        match self {
+            ColorFormat::Hex => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            ColorFormat::Rgb => format!("RGB({},{},{})", r, g, b),
+            ColorFormat::Hsl => {
+                let (h, s, l) = rgb_to_hsl([r, g, b]);
+                format!("HSL({},{}%,{}%)", h, s, l)
+            }
+        }
+    }
+}
+
+/// Convert sRGB to hue in degrees and saturation and lightness in percent
+fn rgb_to_hsl(rgb: [u8; 3]) -> (u32, u32, u32) {
+    let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0, 0, (lightness * 100.0).round() as u32);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (
+        (hue * 60.0).round() as u32 % 360,
+        (saturation * 100.0).round() as u32,
+        (lightness * 100.0).round() as u32,
+    )
+}
+
+pub struct DrawOptions<'a> {
+    pub font: Option<&'a Font>,
+    /// Label text size in pixels; label padding and corners scale with it
+    pub font_size: u32,
+    pub scale: f64,
+    pub palette: Palette,
+    pub unit: Unit,
+    /// Blend label text in linear light instead of sRGB
+    pub linear_blend: bool,
+    /// Anti-alias lines and shapes; off gives hard, pixel-aligned edges. Label
+    /// backgrounds stay anti-aliased either way
+    pub antialias: bool,
+    /// Draw ruler tick marks along measurement lines
+    pub ruler: bool,
+    /// Round measured sizes to a nearby multiple of 5 or 10 for ballpark reading
+    pub round_sizes: bool,
+    /// Pin labels to a screen corner instead of placing them next to the measurement
+    pub label_corner: Option<Corner>,
+    /// Root font size in logical pixels that `Unit::Rem` divides by
+    pub rem_base: f64,
+    /// Continue detected edges to the screen borders as faint alignment guides
+    pub extension_lines: bool,
+    /// Swap reported widths and heights, for a rotated output's native panel orientation
+    pub transposed: bool,
+    /// Size in logical pixels that matching measurements are highlighted for
+    pub target: Option<Size>,
+    /// Capture that line colors are picked against, line by line, when set
+    pub contrast_background: Option<&'a Screenshot>,
+    /// Show the measurement in a fixed strip along this edge instead of a floating label
+    pub status_bar: Option<BarPosition>,
+    /// Physical pixels per millimeter across and down the capture, when the monitor's
+    /// density is known, for showing real-world sizes
+    pub pixels_per_mm: Option<(f64, f64)>,
+    pub real_unit: RealUnit,
+}
+
+impl DrawOptions<'_> {
+    fn font_size(&self) -> f32 {
+        self.font_size as f32
+    }
+
+    /// Label padding `(horizontal, vertical)` for the font size
+    fn label_padding(&self) -> (f32, f32) {
+        let scale = self.font_size() / DEFAULT_FONT_SIZE as f32;
+        (LABEL_PADDING.0 * scale, LABEL_PADDING.1 * scale)
+    }
+
+    fn label_radius(&self) -> f32 {
+        LABEL_RADIUS * self.font_size() / DEFAULT_FONT_SIZE as f32
+    }
+
+    fn label_height(&self) -> f32 {
+        self.font_size() + self.label_padding().1 * 2.0
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        match self.font {
+            Some(font) => text
+                .chars()
+                .map(|c| font.advance_width(c, self.font_size()))
+                .sum(),
+            None => text.chars().count() as f32 * self.font_size() * FALLBACK_ADVANCE,
+        }
+    }
+
+    /// Convert a length in physical pixels to whole display pixels (logical for rem)
+    fn display_length(&self, phys: u32) -> u32 {
+        match self.unit {
+            Unit::Logical | Unit::Rem => (phys as f64 / self.scale).round() as u32,
+            Unit::Device => phys,
+        }
+    }
+
+    /// Length in rem for a physical length
+    fn rem(&self, phys: u32) -> String {
+        format_rem(phys as f64 / self.scale / self.rem_base)
+    }
+
+    /// Format a single physical length for a label
+    fn format_length(&self, phys: u32) -> String {
+        match self.unit {
+            Unit::Logical | Unit::Device => self.display_length(phys).to_string(),
+            Unit::Rem => format!("{}rem ({}px)", self.rem(phys), self.display_length(phys)),
+        }
+    }
+
+    /// A `width` x `height` in the reported orientation
+    fn oriented(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.transposed {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Line color for a measurement of physical `width` x `height`: the match color when it
+    /// is within tolerance of the target size
+    fn line_color(&self, phys_width: u32, phys_height: u32) -> Color {
+        let (phys_width, phys_height) = self.oriented(phys_width, phys_height);
+        let close = |phys: u32, target: u32| {
+            (phys as f64 / self.scale - target as f64).abs() <= TARGET_TOLERANCE
+        };
+        match self.target {
+            Some(target)
+                if close(phys_width, target.width) && close(phys_height, target.height) =>
+            {
+                self.palette.matched
+            }
+            _ => self.palette.line,
+        }
+    }
+
+    /// Color for a line from (x1, y1) to (x2, y2) normally drawn in `color`: the high-contrast
+    /// line color for the content it crosses when lines adapt to the background
+    fn line_color_along(&self, color: Color, x1: f32, y1: f32, x2: f32, y2: f32) -> Color {
+        let Some(screenshot) = self.contrast_background else {
+            return color;
+        };
+        // Keep highlights such as a target match
+        if color != self.palette.line {
+            return color;
+        }
+        let sum: u32 = (0..=CONTRAST_LINE_SAMPLES)
+            .map(|i| {
+                let t = i as f32 / CONTRAST_LINE_SAMPLES as f32;
+                let x = (x1 + (x2 - x1) * t) as u32;
+                let y = (y1 + (y2 - y1) * t) as u32;
+                screenshot.get_luminance(x, y) as u32
+            })
+            .sum();
+        Palette::high_contrast((sum / (CONTRAST_LINE_SAMPLES + 1)) as u8).line
+    }
+
+    /// Format a physical `width` x `height` for a label, marking device pixels
+    fn format_size(&self, phys_width: u32, phys_height: u32) -> String {
+        let (phys_width, phys_height) = self.oriented(phys_width, phys_height);
+        let size = format!(
+            "{} x {}",
+            self.display_length(phys_width),
+            self.display_length(phys_height)
+        );
+        match self.unit {
+            Unit::Logical => size,
+            Unit::Device => format!("{} (device)", size),
+            Unit::Rem => format!(
+                "{} x {}rem ({}px)",
+                self.rem(phys_width),
+                self.rem(phys_height),
+                size
+            ),
+        }
+    }
+
+    /// Format a physical `width` x `height` in real-world units, or `None` when the monitor's
+    /// density isn't known
+    fn format_real_size(&self, phys_width: u32, phys_height: u32) -> Option<String> {
+        let (per_mm_x, per_mm_y) = self.pixels_per_mm?;
+        let (width, height) = (phys_width as f64 / per_mm_x, phys_height as f64 / per_mm_y);
+        // Axes are converted before orienting, since the density is per capture axis
+        let (width, height) = if self.transposed {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        Some(match self.real_unit {
+            RealUnit::Millimeters => format!("{:.1} x {:.1}mm", width, height),
+            RealUnit::Inches => {
+                format!("{:.2} x {:.2}in", width / MM_PER_INCH, height / MM_PER_INCH)
+            }
+        })
+    }
+
+    /// Format a physical `width` x `height` rounded to nearby round numbers, marked with `≈`,
+    /// or `None` when rounding is off or neither length is close to one
+    fn format_rounded_size(&self, phys_width: u32, phys_height: u32) -> Option<String> {
+        if !self.round_sizes || self.unit == Unit::Rem {
+            return None;
+        }
+        let (phys_width, phys_height) = self.oriented(phys_width, phys_height);
+        let (width, height) = (
+            self.display_length(phys_width),
+            self.display_length(phys_height),
+        );
+        let (rounded_width, rounded_height) = (round_length(width), round_length(height));
+        if (rounded_width, rounded_height) == (width, height) {
+            return None;
+        }
+        let size = format!("≈ {} x {}", rounded_width, rounded_height);
+        Some(match self.unit {
+            Unit::Device => format!("{} (device)", size),
+            _ => size,
+        })
+    }
+}
+
+/// The nearest multiple of 10 within 2 of `value`, else of 5 within 1, else `value`
+fn round_length(value: u32) -> u32 {
+    ROUND_STEPS
+        .into_iter()
+        .map(|(step, tolerance)| ((value + step / 2) / step * step, tolerance))
+        .find(|&(rounded, tolerance)| rounded > 0 && rounded.abs_diff(value) <= tolerance)
+        .map_or(value, |(rounded, _)| rounded)
 }
 
-fn fill_color() -> Color {
-    Color::from_rgba8(231, 76, 60, 60)
+/// Format a rem value with up to three decimals and no trailing zeros
+fn format_rem(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
 }
 
-fn label_bg_color() -> Color {
-    Color::from_rgba8(40, 40, 40, 230)
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Format the aspect ratio of `width` x `height`, e.g. `16:9`, falling back to
+/// a decimal like `1.78` when the reduced ratio isn't a simple one
+fn format_aspect_ratio(width: u32, height: u32) -> String {
+    let divisor = gcd(width, height).max(1);
+    let (w, h) = (width / divisor, height / divisor);
+    if w <= MAX_RATIO_TERM && h <= MAX_RATIO_TERM {
+        format!("{}:{}", w, h)
+    } else {
+        format!("{:.2}", width as f64 / height.max(1) as f64)
+    }
 }
 
 fn stroke_line(
@@ -61,14 +605,20 @@ fn stroke_line(
 pub fn draw_measurements(
     pixmap: &mut Pixmap,
     edges: &Edges,
+    corner_radius: Option<u32>,
+    strength: Option<&EdgeStrength>,
     cursor_x: u32,
     cursor_y: u32,
-    font: Option<&fontdue::Font>,
-    scale: f64,
+    opts: &DrawOptions,
 ) {
+    // Add 1 because distance from pixel N to pixel M is M - N + 1 pixels
+    let h_distance = edges.right.saturating_sub(edges.left) + 1;
+    let v_distance = edges.down.saturating_sub(edges.up) + 1;
+    let line_color = opts.line_color(h_distance, v_distance);
+
     let mut paint = Paint::default();
-    paint.set_color(line_color());
-    paint.anti_alias = true;
+    paint.set_color(line_color);
+    paint.anti_alias = opts.antialias;
 
     let stroke = Stroke {
         width: LINE_WIDTH,
@@ -83,158 +633,732 @@ pub fn draw_measurements(
     let cy = cursor_y as f32;
 
     // Horizontal measurement line
-    stroke_line(pixmap, &paint, &stroke, left, cy, right, cy);
-    draw_end_cap(pixmap, &paint, &stroke, left, cy, true);
-    draw_end_cap(pixmap, &paint, &stroke, right, cy, true);
+    let mut h_paint = paint.clone();
+    h_paint.set_color(opts.line_color_along(line_color, left, cy, right, cy));
+    stroke_line(pixmap, &h_paint, &stroke, left, cy, right, cy);
 
     // Vertical measurement line
-    stroke_line(pixmap, &paint, &stroke, cx, up, cx, down);
-    draw_end_cap(pixmap, &paint, &stroke, cx, up, false);
-    draw_end_cap(pixmap, &paint, &stroke, cx, down, false);
+    let mut v_paint = paint.clone();
+    v_paint.set_color(opts.line_color_along(line_color, cx, up, cx, down));
+    stroke_line(pixmap, &v_paint, &stroke, cx, up, cx, down);
 
-    // Dimension label (convert physical pixels to logical pixels)
-    // Add 1 because distance from pixel N to pixel M is M - N + 1 pixels
-    let h_distance = ((edges.right.saturating_sub(edges.left) + 1) as f64 / scale).round() as u32;
-    let v_distance = ((edges.down.saturating_sub(edges.up) + 1) as f64 / scale).round() as u32;
-    let (lx, ly) = get_label_position(cx, cy, pixmap.width(), pixmap.height());
-    draw_label(
-        pixmap,
-        &format!("{} x {}", h_distance, v_distance),
-        lx,
-        ly,
-        font,
+    // With edge strengths, end caps show how far each edge can be trusted
+    let caps = [
+        (&h_paint, strength.map(|s| s.left), left, cy, true),
+        (&h_paint, strength.map(|s| s.right), right, cy, true),
+        (&v_paint, strength.map(|s| s.up), cx, up, false),
+        (&v_paint, strength.map(|s| s.down), cx, down, false),
+    ];
+    for (line_paint, step, x, y, vertical) in caps {
+        let mut cap = line_paint.clone();
+        if let Some(step) = step {
+            cap.set_color(if EdgeStrength::is_crisp(step) {
+                opts.palette.matched
+            } else {
+                opts.palette.warning
+            });
+        }
+        draw_end_cap(pixmap, &cap, &stroke, x, y, vertical);
+    }
+
+    if opts.ruler {
+        draw_ticks(pixmap, &h_paint, left, right, cy, true, opts.scale);
+        draw_ticks(pixmap, &v_paint, up, down, cx, false, opts.scale);
+    }
+
+    if opts.extension_lines {
+        let mut faint = paint.clone();
+        let mut color = line_color;
+        color.apply_opacity(EXTENSION_LINE_OPACITY);
+        faint.set_color(color);
+        let thin = Stroke {
+            width: 1.0,
+            ..Default::default()
+        };
+        let (width, height) = (pixmap.width() as f32, pixmap.height() as f32);
+        for x in [left, right] {
+            stroke_line(pixmap, &faint, &thin, x + 0.5, 0.0, x + 0.5, height);
+        }
+        for y in [up, down] {
+            stroke_line(pixmap, &faint, &thin, 0.0, y + 0.5, width, y + 0.5);
+        }
+    }
+
+    // Dimension label, shown in the status bar instead when there is one
+    if opts.status_bar.is_some() {
+        return;
+    }
+    let text = measurement_text(edges, corner_radius, opts);
+    let (lx, ly) = match opts.label_corner {
+        Some(corner) => pinned_label_position(pixmap, corner, &text, 0, opts),
+        None => get_label_position(cx, cy, pixmap.width(), pixmap.height()),
+    };
+    draw_label(pixmap, &text, lx, ly, opts);
+}
+
+/// Dimension label text for the element bounded by `edges`
+fn measurement_text(edges: &Edges, corner_radius: Option<u32>, opts: &DrawOptions) -> String {
+    let h_distance = edges.right.saturating_sub(edges.left) + 1;
+    let v_distance = edges.down.saturating_sub(edges.up) + 1;
+    let mut text = opts
+        .format_rounded_size(h_distance, v_distance)
+        .unwrap_or_else(|| opts.format_size(h_distance, v_distance));
+    if let Some(real) = opts.format_real_size(h_distance, v_distance) {
+        text.push_str(&format!(", {}", real));
+    }
+    if let Some(radius) = corner_radius {
+        text.push_str(&format!(", r {}", opts.format_length(radius)));
+    }
+    text
+}
+
+/// Draw a free-hand path through physical `points`, labelled with its total length and the
+/// straight-line distance between its ends
+pub fn draw_path(pixmap: &mut Pixmap, points: &[(f32, f32)], opts: &DrawOptions) {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return;
+    };
+
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.line);
+    paint.anti_alias = opts.antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        line_join: LineJoin::Round,
+        ..Default::default()
+    };
+
+    let mut pb = PathBuilder::new();
+    pb.move_to(first.0, first.1);
+    for &(x, y) in &points[1..] {
+        pb.line_to(x, y);
+    }
+    if let Some(path) = pb.finish() {
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+
+    let distance = |a: (f32, f32), b: (f32, f32)| (b.0 - a.0).hypot(b.1 - a.1);
+    let total: f32 = points.windows(2).map(|w| distance(w[0], w[1])).sum();
+    let straight = distance(first, last);
+    let text = format!(
+        "path {}, straight {}",
+        opts.format_length(total.round() as u32),
+        opts.format_length(straight.round() as u32)
     );
+    let (lx, ly) = get_label_position(last.0, last.1, pixmap.width(), pixmap.height());
+    draw_label(pixmap, &text, lx, ly, opts);
 }
 
-pub fn draw_rectangle_measurement(
+/// Draw the circle through `cursor` around `center` (both physical) with its radius line,
+/// labelled with the radius and the angle counter-clockwise from the positive x axis
+pub fn draw_radial_measurement(
     pixmap: &mut Pixmap,
-    x1: u32,
-    y1: u32,
-    x2: u32,
-    y2: u32,
-    font: Option<&fontdue::Font>,
-    scale: f64,
+    center: (u32, u32),
+    cursor: (u32, u32),
+    opts: &DrawOptions,
 ) {
-    let left = x1 as f32;
-    let top = y1 as f32;
-    let right = x2 as f32;
-    let bottom = y2 as f32;
+    let (cx, cy) = (center.0 as f32, center.1 as f32);
+    let (px, py) = (cursor.0 as f32, cursor.1 as f32);
+    let (dx, dy) = (px - cx, py - cy);
+    let radius = dx.hypot(dy);
 
-    // Draw filled rectangle
-    let mut fill_paint = Paint::default();
-    fill_paint.set_color(fill_color());
-    fill_paint.anti_alias = true;
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.line);
+    paint.anti_alias = opts.antialias;
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
 
-    let mut pb = PathBuilder::new();
-    pb.move_to(left, top);
-    pb.line_to(right, top);
-    pb.line_to(right, bottom);
-    pb.line_to(left, bottom);
-    pb.close();
-    if let Some(path) = pb.finish() {
+    if let Some(circle) = PathBuilder::from_circle(cx, cy, radius) {
+        let mut fill = paint.clone();
+        fill.set_color(opts.palette.fill);
         pixmap.fill_path(
-            &path,
-            &fill_paint,
+            &circle,
+            &fill,
             FillRule::Winding,
             Transform::identity(),
             None,
         );
+        pixmap.stroke_path(&circle, &paint, &stroke, Transform::identity(), None);
+    }
+    stroke_line(pixmap, &paint, &stroke, cx, cy, px, py);
+    if let Some(dot) = PathBuilder::from_circle(cx, cy, LINE_WIDTH * 2.0) {
+        pixmap.fill_path(&dot, &paint, FillRule::Winding, Transform::identity(), None);
     }
 
-    // Draw outline
-    let mut stroke_paint = Paint::default();
-    stroke_paint.set_color(line_color());
-    stroke_paint.anti_alias = true;
+    // Screen y grows downwards, so flip it for the usual counter-clockwise angle
+    let angle = (-dy).atan2(dx).to_degrees().rem_euclid(360.0);
+    let text = format!(
+        "r {}, {:.1}°",
+        opts.format_length(radius.round() as u32),
+        angle
+    );
+    // Halfway along the radius line
+    draw_label(pixmap, &text, cx + dx / 2.0, cy + dy / 2.0, opts);
+}
 
+/// Draw the offset from a pinned point to the cursor: dashed horizontal and vertical legs
+/// and a solid line between the two, labelled with the signed dx and dy and the distance
+pub fn draw_pin_delta(
+    pixmap: &mut Pixmap,
+    pin: (u32, u32),
+    cursor: (u32, u32),
+    opts: &DrawOptions,
+) {
+    let (x1, y1) = (pin.0 as f32, pin.1 as f32);
+    let (x2, y2) = (cursor.0 as f32, cursor.1 as f32);
+
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.line);
+    paint.anti_alias = opts.antialias;
     let stroke = Stroke {
         width: LINE_WIDTH,
         ..Default::default()
     };
-
-    // Top edge
-    stroke_line(pixmap, &stroke_paint, &stroke, left, top, right, top);
-    // Bottom edge
-    stroke_line(pixmap, &stroke_paint, &stroke, left, bottom, right, bottom);
-    // Left edge
-    stroke_line(pixmap, &stroke_paint, &stroke, left, top, left, bottom);
-    // Right edge
-    stroke_line(pixmap, &stroke_paint, &stroke, right, top, right, bottom);
-
-    // Draw dimension label (convert physical pixels to logical pixels)
-    let width = ((x2.saturating_sub(x1) + 1) as f64 / scale).round() as u32;
-    let height = ((y2.saturating_sub(y1) + 1) as f64 / scale).round() as u32;
-    // Use physical pixel sizes for layout threshold check
-    let phys_width = x2.saturating_sub(x1) + 1;
-    let phys_height = y2.saturating_sub(y1) + 1;
-    let (lx, ly) = if phys_width >= 150 && phys_height >= 50 {
-        // Center on rectangle if large enough
-        ((left + right) / 2.0, (top + bottom) / 2.0)
-    } else {
-        // Position at bottom center of rectangle
-        let center_x = (left + right) / 2.0;
-        let offset_y = 30.0;
-        let y = if bottom + offset_y > pixmap.height() as f32 - EDGE_THRESHOLD_Y {
-            top - offset_y // Move above if near bottom edge
-        } else {
-            bottom + offset_y
-        };
-        (center_x, y)
+    let dashed = Stroke {
+        dash: StrokeDash::new(DASH_PATTERN.to_vec(), 0.0),
+        ..stroke.clone()
     };
-    draw_label(pixmap, &format!("{} x {}", width, height), lx, ly, font);
-}
 
-fn draw_end_cap(
-    pixmap: &mut Pixmap,
-    paint: &Paint,
-    stroke: &Stroke,
-    x: f32,
-    y: f32,
-    vertical: bool,
-) {
-    let half = END_CAP_SIZE / 2.0;
-    if vertical {
-        stroke_line(pixmap, paint, stroke, x, y - half, x, y + half);
-    } else {
-        stroke_line(pixmap, paint, stroke, x - half, y, x + half, y);
+    stroke_line(pixmap, &paint, &dashed, x1, y1, x2, y1);
+    stroke_line(pixmap, &paint, &dashed, x2, y1, x2, y2);
+    stroke_line(pixmap, &paint, &stroke, x1, y1, x2, y2);
+    if let Some(dot) = PathBuilder::from_circle(x1, y1, LINE_WIDTH * 2.0) {
+        pixmap.fill_path(&dot, &paint, FillRule::Winding, Transform::identity(), None);
     }
+
+    let signed = |from: u32, to: u32| {
+        let sign = if to < from { "-" } else { "+" };
+        format!("{}{}", sign, opts.format_length(from.abs_diff(to)))
+    };
+    let distance = (x2 - x1).hypot(y2 - y1).round() as u32;
+    let text = format!(
+        "dx {}, dy {}, d {}",
+        signed(pin.0, cursor.0),
+        signed(pin.1, cursor.1),
+        opts.format_length(distance)
+    );
+    // Halfway along the line between the pin and the cursor
+    draw_label(pixmap, &text, (x1 + x2) / 2.0, (y1 + y2) / 2.0, opts);
 }
 
-pub fn draw_crosshair(pixmap: &mut Pixmap, x: f32, y: f32) {
+/// Outline the box of the element bounded by `edges`
+pub fn draw_element_outline(pixmap: &mut Pixmap, edges: &Edges, opts: &DrawOptions) {
     let mut paint = Paint::default();
-    paint.set_color(line_color());
-    paint.anti_alias = true;
+    paint.set_color(opts.palette.line);
+    paint.anti_alias = opts.antialias;
 
     let stroke = Stroke {
-        width: 2.0,
+        width: LINE_WIDTH,
         ..Default::default()
     };
 
-    stroke_line(
-        pixmap,
-        &paint,
-        &stroke,
-        x - CROSSHAIR_SIZE,
-        y,
-        x + CROSSHAIR_SIZE,
-        y,
-    );
-    stroke_line(
-        pixmap,
-        &paint,
-        &stroke,
-        x,
-        y - CROSSHAIR_SIZE,
-        x,
-        y + CROSSHAIR_SIZE,
+    let (left, top, right, bottom) = (
+        edges.left as f32,
+        edges.up as f32,
+        edges.right as f32,
+        edges.down as f32,
     );
+    stroke_line(pixmap, &paint, &stroke, left, top, right, top);
+    stroke_line(pixmap, &paint, &stroke, left, bottom, right, bottom);
+    stroke_line(pixmap, &paint, &stroke, left, top, left, bottom);
+    stroke_line(pixmap, &paint, &stroke, right, top, right, bottom);
 }
 
-fn draw_rounded_rect(pixmap: &mut Pixmap, x: f32, y: f32, width: f32, height: f32, radius: f32) {
+/// Draw the whitespace gaps around the element under the cursor, each with its own label
+pub fn draw_gaps(
+    pixmap: &mut Pixmap,
+    gaps: &Gaps,
+    cursor_x: u32,
+    cursor_y: u32,
+    opts: &DrawOptions,
+) {
     let mut paint = Paint::default();
-    paint.set_color(label_bg_color());
-    paint.anti_alias = true;
+    paint.set_color(opts.palette.secondary);
+    paint.anti_alias = opts.antialias;
 
-    let mut pb = PathBuilder::new();
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+
+    let cx = cursor_x as f32;
+    let cy = cursor_y as f32;
+
+    for (start, end) in [gaps.left, gaps.right].into_iter().flatten() {
+        let (start, end) = (start as f32, end as f32);
+        stroke_line(pixmap, &paint, &stroke, start, cy, end, cy);
+        draw_end_cap(pixmap, &paint, &stroke, start, cy, true);
+        draw_end_cap(pixmap, &paint, &stroke, end, cy, true);
+    }
+    for (start, end) in [gaps.up, gaps.down].into_iter().flatten() {
+        let (start, end) = (start as f32, end as f32);
+        stroke_line(pixmap, &paint, &stroke, cx, start, cx, end);
+        draw_end_cap(pixmap, &paint, &stroke, cx, start, false);
+        draw_end_cap(pixmap, &paint, &stroke, cx, end, false);
+    }
+
+    // Labels last so lines don't cover them
+    for (start, end) in [gaps.left, gaps.right].into_iter().flatten() {
+        let text = opts.format_length(end - start + 1);
+        let x = (start + end) as f32 / 2.0;
+        draw_label(pixmap, &text, x, cy - LABEL_OFFSET.1, opts);
+    }
+    for (start, end) in [gaps.up, gaps.down].into_iter().flatten() {
+        let text = opts.format_length(end - start + 1);
+        let y = (start + end) as f32 / 2.0;
+        draw_label(pixmap, &text, cx + LABEL_OFFSET.1, y, opts);
+    }
+}
+
+/// Draw dashed lines from each side of the element bounded by `edges` to the matching screen
+/// border along the cursor's row and column, labelled with the margin
+pub fn draw_margins(
+    pixmap: &mut Pixmap,
+    edges: &Edges,
+    cursor_x: u32,
+    cursor_y: u32,
+    opts: &DrawOptions,
+) {
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.secondary);
+    paint.anti_alias = opts.antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        dash: StrokeDash::new(DASH_PATTERN.to_vec(), 0.0),
+        ..Default::default()
+    };
+
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let (cx, cy) = (cursor_x as f32, cursor_y as f32);
+    // (name, margin in pixels, line from the border to the element)
+    let margins = [
+        ("left", edges.left, (0.0, cy, edges.left as f32, cy)),
+        (
+            "right",
+            (width - 1).saturating_sub(edges.right),
+            (edges.right as f32, cy, width as f32, cy),
+        ),
+        ("top", edges.up, (cx, 0.0, cx, edges.up as f32)),
+        (
+            "bottom",
+            (height - 1).saturating_sub(edges.down),
+            (cx, edges.down as f32, cx, height as f32),
+        ),
+    ];
+
+    for &(_, margin, (x1, y1, x2, y2)) in &margins {
+        if margin > 0 {
+            stroke_line(pixmap, &paint, &stroke, x1, y1, x2, y2);
+        }
+    }
+
+    // Labels last so lines don't cover them
+    for (name, margin, (x1, y1, x2, y2)) in margins {
+        if margin == 0 {
+            continue;
+        }
+        let text = format!("{} margin: {}", name, opts.format_length(margin));
+        let (x, y) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+        let (x, y) = if y1 == y2 {
+            (x, y - LABEL_OFFSET.1)
+        } else {
+            (x + LABEL_OFFSET.0, y)
+        };
+        draw_label(pixmap, &text, x, y, opts);
+    }
+}
+
+/// Outline both boxes of a bordered element in distinct colors, labelled below with the
+/// outer and inner sizes and the border width
+pub fn draw_border_box(pixmap: &mut Pixmap, border: &BorderBox, opts: &DrawOptions) {
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+    for (edges, color) in [
+        (&border.outer, opts.palette.line),
+        (&border.inner, opts.palette.secondary),
+    ] {
+        let mut paint = Paint::default();
+        paint.set_color(color);
+        paint.anti_alias = opts.antialias;
+        let (left, top, right, bottom) = (
+            edges.left as f32,
+            edges.up as f32,
+            edges.right as f32 + 1.0,
+            edges.down as f32 + 1.0,
+        );
+        stroke_line(pixmap, &paint, &stroke, left, top, right, top);
+        stroke_line(pixmap, &paint, &stroke, left, bottom, right, bottom);
+        stroke_line(pixmap, &paint, &stroke, left, top, left, bottom);
+        stroke_line(pixmap, &paint, &stroke, right, top, right, bottom);
+    }
+
+    let size =
+        |edges: &Edges| opts.format_size(edges.right - edges.left + 1, edges.down - edges.up + 1);
+    // One width when the border is even, else all four in CSS order
+    let widths = match border.widths() {
+        (top, right, bottom, left) if top == right && right == bottom && bottom == left => {
+            opts.format_length(top)
+        }
+        (top, right, bottom, left) => [top, right, bottom, left]
+            .map(|width| opts.format_length(width))
+            .join(" "),
+    };
+    let text = format!(
+        "outer {}, inner {}, border {}",
+        size(&border.outer),
+        size(&border.inner),
+        widths
+    );
+    let outer = &border.outer;
+    let x = (outer.left + outer.right) as f32 / 2.0;
+    let y = outer.down as f32 + LINE_WIDTH + opts.label_height() / 2.0;
+    draw_label(pixmap, &text, x, y, opts);
+}
+
+/// Mark the baseline of each detected text line across the scanned columns, with tick marks
+/// up to each line's top, and label the line and cap heights beside the cursor
+pub fn draw_baselines(
+    pixmap: &mut Pixmap,
+    text: &TextLines,
+    cursor_x: u32,
+    cursor_y: u32,
+    opts: &DrawOptions,
+) {
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.secondary);
+    paint.anti_alias = opts.antialias;
+    let stroke = Stroke {
+        width: 1.0,
+        ..Default::default()
+    };
+
+    let (x1, x2) = (text.x1 as f32, text.x2 as f32 + 1.0);
+    for line in &text.lines {
+        let baseline = line.baseline as f32 + 1.0;
+        stroke_line(pixmap, &paint, &stroke, x1, baseline, x2, baseline);
+        stroke_line(pixmap, &paint, &stroke, x1, line.top as f32, x1, baseline);
+    }
+
+    let mut fields = Vec::new();
+    if let Some(height) = text.line_height() {
+        fields.push(format!("line height {}", opts.format_length(height)));
+    }
+    if let Some(height) = text.cap_height() {
+        fields.push(format!("cap height {}", opts.format_length(height)));
+    }
+    if fields.is_empty() {
+        return;
+    }
+    // Above the cursor, clear of the dimension label below it
+    let (cx, cy) = (cursor_x as f32, cursor_y as f32);
+    let y = if cy > LABEL_OFFSET.1 * 2.0 {
+        cy - LABEL_OFFSET.1
+    } else {
+        cy + LABEL_OFFSET.1 * 2.0
+    };
+    let (x, _) = get_label_position(cx, cy, pixmap.width(), pixmap.height());
+    draw_label(pixmap, &fields.join(", "), x, y, opts);
+}
+
+/// Draw a rectangle spanning corners (x1, y1) and (x2, y2) in any order.
+/// When `live`, (x2, y2) is the corner under the cursor and the label is pinned next to it.
+pub fn draw_rectangle_measurement(
+    pixmap: &mut Pixmap,
+    x1: u32,
+    y1: u32,
+    x2: u32,
+    y2: u32,
+    live: bool,
+    opts: &DrawOptions,
+) {
+    let (corner_x, corner_y) = (x2 as f32, y2 as f32);
+    let (grows_right, grows_down) = (x2 >= x1, y2 >= y1);
+    let (x1, y1, x2, y2) = (x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2));
+    let left = x1 as f32;
+    let top = y1 as f32;
+    let right = x2 as f32;
+    let bottom = y2 as f32;
+
+    // Draw filled rectangle
+    let mut fill_paint = Paint::default();
+    fill_paint.set_color(opts.palette.fill);
+    fill_paint.anti_alias = opts.antialias;
+
+    let mut pb = PathBuilder::new();
+    pb.move_to(left, top);
+    pb.line_to(right, top);
+    pb.line_to(right, bottom);
+    pb.line_to(left, bottom);
+    pb.close();
+    if let Some(path) = pb.finish() {
+        pixmap.fill_path(
+            &path,
+            &fill_paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    // Draw outline, highlighted when it matches the target size
+    let phys_width = x2.saturating_sub(x1) + 1;
+    let phys_height = y2.saturating_sub(y1) + 1;
+    let outline_color = opts.line_color(phys_width, phys_height);
+    let mut stroke_paint = Paint::default();
+    stroke_paint.set_color(outline_color);
+    stroke_paint.anti_alias = opts.antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+
+    // Top, bottom, left and right edges, each colored against the content it crosses
+    for (from_x, from_y, to_x, to_y) in [
+        (left, top, right, top),
+        (left, bottom, right, bottom),
+        (left, top, left, bottom),
+        (right, top, right, bottom),
+    ] {
+        let color = opts.line_color_along(outline_color, from_x, from_y, to_x, to_y);
+        stroke_paint.set_color(color);
+        stroke_line(pixmap, &stroke_paint, &stroke, from_x, from_y, to_x, to_y);
+    }
+
+    // Draw dimension label, using physical pixel sizes for the layout threshold check
+    let mut size = opts.format_size(phys_width, phys_height);
+    if let Some(real) = opts.format_real_size(phys_width, phys_height) {
+        size.push_str(&format!(", {}", real));
+    }
+    let text = format!(
+        "{} ({})",
+        size,
+        format_aspect_ratio(
+            opts.display_length(phys_width),
+            opts.display_length(phys_height)
+        )
+    );
+    let (lx, ly) = if let Some(corner) = opts.label_corner {
+        // Stack below (or above) the cursor measurement label in the same corner
+        pinned_label_position(pixmap, corner, &text, 1, opts)
+    } else if live {
+        // Keep the label just outside the cursor corner so it doesn't jump while dragging
+        let x = if grows_right {
+            corner_x + LABEL_OFFSET.0
+        } else {
+            corner_x - LABEL_OFFSET.0
+        };
+        let y = if grows_down {
+            corner_y + LABEL_OFFSET.1
+        } else {
+            corner_y - LABEL_OFFSET.1
+        };
+        (
+            x.clamp(LABEL_OFFSET.0, pixmap.width() as f32 - LABEL_OFFSET.0),
+            y.clamp(LABEL_OFFSET.1, pixmap.height() as f32 - LABEL_OFFSET.1),
+        )
+    } else if phys_width >= 150 && phys_height >= 50 {
+        // Center on rectangle if large enough
+        ((left + right) / 2.0, (top + bottom) / 2.0)
+    } else {
+        // Position at bottom center of rectangle
+        let center_x = (left + right) / 2.0;
+        let offset_y = 30.0;
+        let y = if bottom + offset_y > pixmap.height() as f32 - EDGE_THRESHOLD_Y {
+            top - offset_y // Move above if near bottom edge
+        } else {
+            bottom + offset_y
+        };
+        (center_x, y)
+    };
+    draw_label(pixmap, &text, lx, ly, opts);
+}
+
+/// Highlight edges of different rectangles that nearly, but not exactly, line up.
+/// Both offending edges are extended across the two rectangles and labelled with their offset.
+pub fn draw_misalignments(pixmap: &mut Pixmap, rects: &[(u32, u32, u32, u32)], opts: &DrawOptions) {
+    let tolerance = (MISALIGN_TOLERANCE * opts.scale).round() as u32;
+
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.warning);
+    paint.anti_alias = opts.antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+
+    for (i, a) in rects.iter().enumerate() {
+        for b in &rects[i + 1..] {
+            // Left and right edges
+            for (ea, eb) in [(a.0, b.0), (a.2, b.2)] {
+                let delta = ea.abs_diff(eb);
+                if delta == 0 || delta > tolerance {
+                    continue;
+                }
+                let top = a.1.min(b.1) as f32;
+                let bottom = a.3.max(b.3) as f32;
+                stroke_line(pixmap, &paint, &stroke, ea as f32, top, ea as f32, bottom);
+                stroke_line(pixmap, &paint, &stroke, eb as f32, top, eb as f32, bottom);
+                let text = format!("Δ{}", opts.format_length(delta));
+                draw_label(
+                    pixmap,
+                    &text,
+                    (ea + eb) as f32 / 2.0,
+                    (top + bottom) / 2.0,
+                    opts,
+                );
+            }
+
+            // Top and bottom edges
+            for (ea, eb) in [(a.1, b.1), (a.3, b.3)] {
+                let delta = ea.abs_diff(eb);
+                if delta == 0 || delta > tolerance {
+                    continue;
+                }
+                let left = a.0.min(b.0) as f32;
+                let right = a.2.max(b.2) as f32;
+                stroke_line(pixmap, &paint, &stroke, left, ea as f32, right, ea as f32);
+                stroke_line(pixmap, &paint, &stroke, left, eb as f32, right, eb as f32);
+                let text = format!("Δ{}", opts.format_length(delta));
+                draw_label(
+                    pixmap,
+                    &text,
+                    (left + right) / 2.0,
+                    (ea + eb) as f32 / 2.0,
+                    opts,
+                );
+            }
+        }
+    }
+}
+
+/// Draw ruler ticks from `start` to `end` along a line at `fixed`, spaced in logical pixels
+fn draw_ticks(
+    pixmap: &mut Pixmap,
+    paint: &Paint,
+    start: f32,
+    end: f32,
+    fixed: f32,
+    horizontal: bool,
+    scale: f64,
+) {
+    let stroke = Stroke {
+        width: 1.0,
+        ..Default::default()
+    };
+    let spacing = RULER_TICK_SPACING as f32 * scale as f32;
+    let mut i = 1;
+    loop {
+        let pos = start + i as f32 * spacing;
+        if pos >= end {
+            break;
+        }
+        let half = if (i * RULER_TICK_SPACING).is_multiple_of(RULER_MAJOR_SPACING) {
+            RULER_MAJOR_TICK_SIZE
+        } else {
+            RULER_TICK_SIZE
+        };
+        if horizontal {
+            stroke_line(pixmap, paint, &stroke, pos, fixed - half, pos, fixed + half);
+        } else {
+            stroke_line(pixmap, paint, &stroke, fixed - half, pos, fixed + half, pos);
+        }
+        i += 1;
+    }
+}
+
+fn draw_end_cap(
+    pixmap: &mut Pixmap,
+    paint: &Paint,
+    stroke: &Stroke,
+    x: f32,
+    y: f32,
+    vertical: bool,
+) {
+    let half = END_CAP_SIZE / 2.0;
+    if vertical {
+        stroke_line(pixmap, paint, stroke, x, y - half, x, y + half);
+    } else {
+        stroke_line(pixmap, paint, stroke, x - half, y, x + half, y);
+    }
+}
+
+pub fn draw_crosshair(
+    pixmap: &mut Pixmap,
+    x: f32,
+    y: f32,
+    style: CrosshairStyle,
+    palette: &Palette,
+    antialias: bool,
+) {
+    let mut paint = Paint::default();
+    paint.set_color(palette.line);
+    paint.anti_alias = antialias;
+
+    match style {
+        CrosshairStyle::Cross => {
+            let stroke = Stroke {
+                width: 2.0,
+                ..Default::default()
+            };
+            stroke_line(
+                pixmap,
+                &paint,
+                &stroke,
+                x - CROSSHAIR_SIZE,
+                y,
+                x + CROSSHAIR_SIZE,
+                y,
+            );
+            stroke_line(
+                pixmap,
+                &paint,
+                &stroke,
+                x,
+                y - CROSSHAIR_SIZE,
+                x,
+                y + CROSSHAIR_SIZE,
+            );
+        }
+        CrosshairStyle::Dot => {
+            if let Some(rect) = Rect::from_xywh(x, y, 1.0, 1.0) {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
+        CrosshairStyle::Lines => {
+            // Centered on the pixel so the hairlines stay one pixel wide
+            let stroke = Stroke {
+                width: 1.0,
+                ..Default::default()
+            };
+            let (width, height) = (pixmap.width() as f32, pixmap.height() as f32);
+            stroke_line(pixmap, &paint, &stroke, 0.0, y + 0.5, width, y + 0.5);
+            stroke_line(pixmap, &paint, &stroke, x + 0.5, 0.0, x + 0.5, height);
+        }
+    }
+}
+
+fn draw_rounded_rect(
+    pixmap: &mut Pixmap,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    radius: f32,
+    color: Color,
+) {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+
+    let mut pb = PathBuilder::new();
     pb.move_to(x + radius, y);
     pb.line_to(x + width - radius, y);
     pb.quad_to(x + width, y, x + width, y + radius);
@@ -257,25 +1381,59 @@ fn draw_rounded_rect(pixmap: &mut Pixmap, x: f32, y: f32, width: f32, height: f3
     }
 }
 
-fn blend_pixel(pixel: &PremultipliedColorU8, alpha: f32) -> Option<PremultipliedColorU8> {
+fn blend_pixel(
+    pixel: &PremultipliedColorU8,
+    alpha: f32,
+    color: ColorU8,
+    linear: bool,
+) -> Option<PremultipliedColorU8> {
+    if linear {
+        // Source-over in linear light on un-premultiplied values
+        let dst_a = pixel.alpha() as f32 / 255.0;
+        let out_a = alpha + dst_a * (1.0 - alpha);
+        let blend = |dst: u8, src: u8| {
+            let dst = if pixel.alpha() == 0 {
+                0.0
+            } else {
+                srgb_to_linear((dst as u32 * 255 / pixel.alpha() as u32) as u8)
+            };
+            let out = (srgb_to_linear(src) * alpha + dst * dst_a * (1.0 - alpha)) / out_a;
+            (linear_to_srgb(out) as f32 * out_a) as u8
+        };
+        return PremultipliedColorU8::from_rgba(
+            blend(pixel.red(), color.red()),
+            blend(pixel.green(), color.green()),
+            blend(pixel.blue(), color.blue()),
+            (out_a * 255.0) as u8,
+        );
+    }
+
     let inv_a = 1.0 - alpha;
     let max_val = pixel.alpha() as f32;
     PremultipliedColorU8::from_rgba(
-        ((inv_a * pixel.red() as f32 + alpha * 255.0).min(max_val)) as u8,
-        ((inv_a * pixel.green() as f32 + alpha * 255.0).min(max_val)) as u8,
-        ((inv_a * pixel.blue() as f32 + alpha * 255.0).min(max_val)) as u8,
+        ((inv_a * pixel.red() as f32 + alpha * color.red() as f32).min(max_val)) as u8,
+        ((inv_a * pixel.green() as f32 + alpha * color.green() as f32).min(max_val)) as u8,
+        ((inv_a * pixel.blue() as f32 + alpha * color.blue() as f32).min(max_val)) as u8,
         (inv_a * pixel.alpha() as f32 + alpha * 255.0) as u8,
     )
 }
 
-fn draw_text(pixmap: &mut Pixmap, font: &fontdue::Font, text: &str, start_x: f32, baseline_y: f32) {
+fn draw_text(
+    pixmap: &mut Pixmap,
+    font: &Font,
+    text: &str,
+    start_x: f32,
+    baseline_y: f32,
+    opts: &DrawOptions,
+) {
     let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
     let stride = width as usize;
     let pixels = pixmap.pixels_mut();
 
     let mut cursor_x = start_x;
     for c in text.chars() {
-        let (metrics, bitmap) = font.rasterize(c, FONT_SIZE);
+        let glyph = font.rasterize(c, opts.font_size());
+        let (metrics, bitmap) = &*glyph;
 
         for py in 0..metrics.height {
             for px in 0..metrics.width {
@@ -292,7 +1450,12 @@ fn draw_text(pixmap: &mut Pixmap, font: &fontdue::Font, text: &str, start_x: f32
                 }
 
                 let idx = draw_y as usize * stride + draw_x as usize;
-                if let Some(new_pixel) = blend_pixel(&pixels[idx], alpha as f32 / 255.0) {
+                if let Some(new_pixel) = blend_pixel(
+                    &pixels[idx],
+                    alpha as f32 / 255.0,
+                    opts.palette.label_text,
+                    opts.linear_blend,
+                ) {
                     pixels[idx] = new_pixel;
                 }
             }
@@ -301,16 +1464,39 @@ fn draw_text(pixmap: &mut Pixmap, font: &fontdue::Font, text: &str, start_x: f32
     }
 }
 
-fn draw_label(pixmap: &mut Pixmap, text: &str, x: f32, y: f32, font: Option<&fontdue::Font>) {
-    let mut text_width = 0.0;
-    if let Some(font) = font {
-        for c in text.chars() {
-            let metrics = font.metrics(c, FONT_SIZE);
-            text_width += metrics.advance_width;
+/// Center of a label pinned to `corner`, stacked `slot` labels away from the corner
+fn pinned_label_position(
+    pixmap: &Pixmap,
+    corner: Corner,
+    text: &str,
+    slot: usize,
+    opts: &DrawOptions,
+) -> (f32, f32) {
+    let padding = opts.label_padding();
+    let label_width = opts.text_width(text) + padding.0 * 2.0;
+    let label_height = opts.label_height();
+    let stack_offset = slot as f32 * (label_height + CORNER_MARGIN / 2.0);
+
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => CORNER_MARGIN + label_width / 2.0,
+        Corner::TopRight | Corner::BottomRight => {
+            pixmap.width() as f32 - CORNER_MARGIN - label_width / 2.0
         }
-    }
-    let label_width = text_width + LABEL_PADDING.0 * 2.0;
-    let label_height = FONT_SIZE + LABEL_PADDING.1 * 2.0;
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => CORNER_MARGIN + label_height / 2.0 + stack_offset,
+        Corner::BottomLeft | Corner::BottomRight => {
+            pixmap.height() as f32 - CORNER_MARGIN - label_height / 2.0 - stack_offset
+        }
+    };
+    (x, y)
+}
+
+fn draw_label(pixmap: &mut Pixmap, text: &str, x: f32, y: f32, opts: &DrawOptions) {
+    let padding = opts.label_padding();
+    let text_width = opts.text_width(text);
+    let label_width = text_width + padding.0 * 2.0;
+    let label_height = opts.label_height();
     let label_x = x - label_width / 2.0;
     let label_y = y - label_height / 2.0;
 
@@ -320,12 +1506,832 @@ fn draw_label(pixmap: &mut Pixmap, text: &str, x: f32, y: f32, font: Option<&fon
         label_y,
         label_width,
         label_height,
-        LABEL_RADIUS,
+        opts.label_radius(),
+        opts.palette.label_bg,
+    );
+
+    let text_x = label_x + padding.0;
+    let baseline_y = label_y + padding.1 + opts.font_size() * 0.8;
+    match opts.font {
+        Some(font) => draw_text(pixmap, font, text, text_x, baseline_y, opts),
+        None => draw_fallback_digits(pixmap, text, text_x, baseline_y, opts),
+    }
+}
+
+/// Draw the digits, `-`, `x`, `.` and `,` of `text` as stroked seven-segment glyphs so
+/// measurements stay readable without a font. Other characters are left as blank space.
+fn draw_fallback_digits(
+    pixmap: &mut Pixmap,
+    text: &str,
+    start_x: f32,
+    baseline_y: f32,
+    opts: &DrawOptions,
+) {
+    let size = opts.font_size();
+    let (height, width) = (size * FALLBACK_GLYPH_SIZE.0, size * FALLBACK_GLYPH_SIZE.1);
+    let color = opts.palette.label_text;
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(color.red(), color.green(), color.blue(), color.alpha());
+    paint.anti_alias = true;
+    let stroke = Stroke {
+        width: size * FALLBACK_STROKE,
+        line_cap: LineCap::Round,
+        ..Default::default()
+    };
+
+    let (top, middle, bottom) = (baseline_y - height, baseline_y - height / 2.0, baseline_y);
+    for (i, c) in text.chars().enumerate() {
+        let left = start_x + i as f32 * size * FALLBACK_ADVANCE;
+        let right = left + width;
+        let segments = match c {
+            '0'..='9' => SEGMENT_DIGITS[c as usize - '0' as usize],
+            '-' => 0b1000000,
+            _ => 0,
+        };
+        let mut lines = [
+            (left, top, right, top),
+            (right, top, right, middle),
+            (right, middle, right, bottom),
+            (left, bottom, right, bottom),
+            (left, middle, left, bottom),
+            (left, top, left, middle),
+            (left, middle, right, middle),
+        ]
+        .into_iter()
+        .enumerate()
+        .filter(|&(segment, _)| segments & (1 << segment) != 0)
+        .map(|(_, line)| line)
+        .collect::<Vec<_>>();
+        match c {
+            'x' => {
+                let x_top = baseline_y - height * 0.6;
+                lines.push((left, x_top, right, bottom));
+                lines.push((right, x_top, left, bottom));
+            }
+            // A stroke with round caps draws a dot
+            '.' | ',' => lines.push((left + width / 2.0, bottom, left + width / 2.0, bottom)),
+            _ => {}
+        }
+        for (x1, y1, x2, y2) in lines {
+            stroke_line(pixmap, &paint, &stroke, x1, y1, x2, y2);
+        }
+    }
+}
+
+fn stroke_dashed_rect(
+    pixmap: &mut Pixmap,
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    color: Color,
+    antialias: bool,
+) {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        dash: StrokeDash::new(DASH_PATTERN.to_vec(), 0.0),
+        ..Default::default()
+    };
+
+    stroke_line(pixmap, &paint, &stroke, left, top, right, top);
+    stroke_line(pixmap, &paint, &stroke, left, bottom, right, bottom);
+    stroke_line(pixmap, &paint, &stroke, left, top, left, bottom);
+    stroke_line(pixmap, &paint, &stroke, right, top, right, bottom);
+}
+
+/// Outline the region edge detection is confined to with a dashed line
+pub fn draw_scan_bounds(pixmap: &mut Pixmap, bounds: ScanBounds, opts: &DrawOptions) {
+    stroke_dashed_rect(
+        pixmap,
+        bounds.0 as f32,
+        bounds.1 as f32,
+        bounds.2 as f32,
+        bounds.3 as f32,
+        opts.palette.secondary,
+        opts.antialias,
+    );
+}
+
+/// Outline the focused window, given as physical `(x, y, width, height)`, and label the
+/// cursor position relative to its top-left corner
+pub fn draw_window_origin(
+    pixmap: &mut Pixmap,
+    window: (i32, i32, i32, i32),
+    cursor_x: u32,
+    cursor_y: u32,
+    opts: &DrawOptions,
+) {
+    let (x, y, width, height) = window;
+    stroke_dashed_rect(
+        pixmap,
+        x as f32,
+        y as f32,
+        (x + width - 1) as f32,
+        (y + height - 1) as f32,
+        opts.palette.warning,
+        opts.antialias,
+    );
+
+    let offset = |cursor: u32, origin: i32| {
+        let delta = cursor as i64 - origin as i64;
+        let sign = if delta < 0 { "-" } else { "" };
+        format!(
+            "{}{}",
+            sign,
+            opts.display_length(delta.unsigned_abs() as u32)
+        )
+    };
+    let text = format!("{}, {}", offset(cursor_x, x), offset(cursor_y, y));
+    let (lx, ly) = get_label_position(
+        cursor_x as f32,
+        cursor_y as f32,
+        pixmap.width(),
+        pixmap.height(),
     );
+    // Mirror the measurement label's placement so the two don't overlap
+    let (lx, ly) = (2.0 * cursor_x as f32 - lx, 2.0 * cursor_y as f32 - ly);
+    draw_label(pixmap, &text, lx, ly, opts);
+}
+
+/// Paint every pixel set in the row-major edge `mask`, `mask_width` pixels wide, in the
+/// secondary color
+pub fn draw_edge_map(pixmap: &mut Pixmap, mask: &[bool], mask_width: u32, opts: &DrawOptions) {
+    let color = opts.palette.secondary.premultiply().to_color_u8();
+    let width = pixmap.width() as usize;
+    let rows = pixmap.pixels_mut().chunks_exact_mut(width);
+    for (row, mask_row) in rows.zip(mask.chunks_exact(mask_width as usize)) {
+        for (pixel, &edge) in row.iter_mut().zip(mask_row) {
+            if edge {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+/// Tint the pixels that changed since the reference capture, then outline and size each
+/// changed region
+pub fn draw_diff(pixmap: &mut Pixmap, diff: &Diff, mask_width: u32, opts: &DrawOptions) {
+    let mut tint = opts.palette.warning;
+    tint.apply_opacity(DIFF_OPACITY);
+    let tint = tint.premultiply().to_color_u8();
+    let width = pixmap.width() as usize;
+    let rows = pixmap.pixels_mut().chunks_exact_mut(width);
+    for (row, mask_row) in rows.zip(diff.mask.chunks_exact(mask_width as usize)) {
+        for (pixel, &changed) in row.iter_mut().zip(mask_row) {
+            if changed {
+                *pixel = tint;
+            }
+        }
+    }
+
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.warning);
+    paint.anti_alias = opts.antialias;
+
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+
+    for &(x1, y1, x2, y2) in &diff.regions {
+        let (left, top, right, bottom) = (x1 as f32, y1 as f32, x2 as f32 + 1.0, y2 as f32 + 1.0);
+        stroke_line(pixmap, &paint, &stroke, left, top, right, top);
+        stroke_line(pixmap, &paint, &stroke, left, bottom, right, bottom);
+        stroke_line(pixmap, &paint, &stroke, left, top, left, bottom);
+        stroke_line(pixmap, &paint, &stroke, right, top, right, bottom);
+
+        let text = opts.format_size(x2 - x1 + 1, y2 - y1 + 1);
+        let ly = bottom + opts.label_height() / 2.0 + LINE_WIDTH;
+        draw_label(pixmap, &text, (left + right) / 2.0, ly, opts);
+    }
+}
+
+/// Draw the full screen size in device and logical pixels as a centered label
+pub fn draw_screen_size(pixmap: &mut Pixmap, opts: &DrawOptions) {
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let logical = |phys: u32| (phys as f64 / opts.scale).round() as u32;
+    let (across, down) = if opts.transposed {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let text = format!(
+        "Screen {} x {} device, {} x {} logical @ {}x",
+        across,
+        down,
+        logical(across),
+        logical(down),
+        (opts.scale * 100.0).round() / 100.0
+    );
+    draw_label(pixmap, &text, width as f32 / 2.0, height as f32 / 2.0, opts);
+}
+
+/// Sizes of measurements added up, in physical pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunningTotal {
+    pub width: u32,
+    pub height: u32,
+    pub count: u32,
+}
+
+impl RunningTotal {
+    /// Add the size of the inclusive `(x1, y1, x2, y2)` rectangle
+    pub fn add(&mut self, (x1, y1, x2, y2): (u32, u32, u32, u32)) {
+        self.width += x2.saturating_sub(x1) + 1;
+        self.height += y2.saturating_sub(y1) + 1;
+        self.count += 1;
+    }
+}
+
+/// Label the running total in the pinned label corner (top-right if labels aren't
+/// pinned), below the labels pinned there
+pub fn draw_running_total(pixmap: &mut Pixmap, total: &RunningTotal, opts: &DrawOptions) {
+    let text = if total.count == 0 {
+        "Total: click or drag to add".to_string()
+    } else {
+        format!(
+            "Total {} ({} added)",
+            opts.format_size(total.width, total.height),
+            total.count
+        )
+    };
+    let corner = opts.label_corner.unwrap_or(Corner::TopRight);
+    let (x, y) = pinned_label_position(pixmap, corner, &text, 2, opts);
+    draw_label(pixmap, &text, x, y, opts);
+}
+
+/// Draw the color under the cursor in every format as labels stacked beside it, with
+/// `prominent` first next to a swatch of the color
+pub fn draw_color_readout(
+    pixmap: &mut Pixmap,
+    rgb: [u8; 3],
+    prominent: ColorFormat,
+    cx: f32,
+    cy: f32,
+    opts: &DrawOptions,
+) {
+    let padding = opts.label_padding();
+    let rows: Vec<String> = std::iter::once(prominent)
+        .chain(ColorFormat::ALL.into_iter().filter(|&f| f != prominent))
+        .map(|format| format.format(rgb))
+        .collect();
+    let label_height = opts.label_height();
+    let row_height = label_height + padding.1;
+    let width = rows
+        .iter()
+        .map(|row| opts.text_width(row) + padding.0 * 2.0)
+        .fold(0.0, f32::max);
+    let swatch = label_height;
+
+    // Below and to the right of the cursor, flipped to stay on screen
+    let total_width = swatch + padding.1 + width;
+    let total_height = rows.len() as f32 * row_height;
+    let x = if cx + CROSSHAIR_SIZE + total_width > pixmap.width() as f32 {
+        cx - CROSSHAIR_SIZE - total_width
+    } else {
+        cx + CROSSHAIR_SIZE
+    };
+    let y = if cy + CROSSHAIR_SIZE + total_height > pixmap.height() as f32 {
+        cy - CROSSHAIR_SIZE - total_height
+    } else {
+        cy + CROSSHAIR_SIZE
+    };
+
+    draw_rounded_rect(
+        pixmap,
+        x,
+        y,
+        swatch,
+        swatch,
+        opts.label_radius(),
+        opts.palette.label_bg,
+    );
+    let [r, g, b] = rgb;
+    draw_rounded_rect(
+        pixmap,
+        x + 2.0,
+        y + 2.0,
+        swatch - 4.0,
+        swatch - 4.0,
+        opts.label_radius() - 2.0,
+        Color::from_rgba8(r, g, b, 255),
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let row_width = opts.text_width(row) + padding.0 * 2.0;
+        draw_label(
+            pixmap,
+            row,
+            x + swatch + padding.1 + row_width / 2.0,
+            y + i as f32 * row_height + label_height / 2.0,
+            opts,
+        );
+    }
+}
+
+/// Draw a magnified view of the captured pixels around physical (cx, cy), `zoom` screen
+/// pixels per device pixel, beside the cursor with the pixel under it outlined. Sampling the
+/// physical capture rather than logical coordinates shows the real device pixels on scaled
+/// outputs, and at higher zoom a grid marks their boundaries.
+pub fn draw_loupe(
+    pixmap: &mut Pixmap,
+    screenshot: &Screenshot,
+    cx: u32,
+    cy: u32,
+    zoom: u32,
+    opts: &DrawOptions,
+) {
+    // An odd number of source pixels keeps the cursor pixel in the middle
+    let span = (LOUPE_SIZE / zoom) | 1;
+    let side = span * zoom;
+    let offset = CROSSHAIR_SIZE as u32 * 2;
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let x = if cx + offset + side < width {
+        cx + offset
+    } else {
+        cx.saturating_sub(offset + side)
+    };
+    let y = if cy >= offset + side {
+        cy - offset - side
+    } else {
+        (cy + offset).min(height.saturating_sub(side))
+    };
+
+    draw_rounded_rect(
+        pixmap,
+        x as f32 - LOUPE_BORDER,
+        y as f32 - LOUPE_BORDER,
+        side as f32 + LOUPE_BORDER * 2.0,
+        side as f32 + LOUPE_BORDER * 2.0,
+        opts.label_radius(),
+        opts.palette.label_bg,
+    );
+
+    let stride = width as usize;
+    let pixels = pixmap.pixels_mut();
+    let half = span / 2;
+    for sy in 0..span {
+        for sx in 0..span {
+            let (Some(src_x), Some(src_y)) =
+                ((cx + sx).checked_sub(half), (cy + sy).checked_sub(half))
+            else {
+                continue;
+            };
+            let Some([r, g, b]) = screenshot.rgb(src_x, src_y) else {
+                continue;
+            };
+            let color = PremultipliedColorU8::from_rgba(r, g, b, 255).unwrap();
+            for py in y + sy * zoom..(y + (sy + 1) * zoom).min(height) {
+                for px in x + sx * zoom..(x + (sx + 1) * zoom).min(width) {
+                    pixels[py as usize * stride + px as usize] = color;
+                }
+            }
+        }
+    }
+
+    if zoom >= LOUPE_GRID_MIN_ZOOM {
+        let mut grid = Paint::default();
+        let mut color = opts.palette.label_bg;
+        color.set_alpha(LOUPE_GRID_OPACITY);
+        grid.set_color(color);
+        for i in 1..span {
+            let offset = (i * zoom) as f32;
+            let lines = [
+                Rect::from_xywh(x as f32 + offset, y as f32, 1.0, side as f32),
+                Rect::from_xywh(x as f32, y as f32 + offset, side as f32, 1.0),
+            ];
+            for line in lines.into_iter().flatten() {
+                pixmap.fill_rect(line, &grid, Transform::identity(), None);
+            }
+        }
+    }
+
+    let mut paint = Paint::default();
+    paint.set_color(opts.palette.line);
+    let stroke = Stroke {
+        width: LINE_WIDTH,
+        ..Default::default()
+    };
+    let center = (x + half * zoom) as f32;
+    let middle = (y + half * zoom) as f32;
+    if let Some(rect) = Rect::from_xywh(center, middle, zoom as f32, zoom as f32) {
+        let path = PathBuilder::from_rect(rect);
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+}
+
+/// Draw a full-width strip along `position` reading out the cursor position, the measured
+/// element's size and the color under the cursor. `cursor` is read out as given, so callers
+/// map it into native orientation when that is on.
+pub fn draw_status_bar(
+    pixmap: &mut Pixmap,
+    position: BarPosition,
+    cursor: (u32, u32),
+    measurement: Option<(&Edges, Option<u32>)>,
+    color: Option<(ColorFormat, [u8; 3])>,
+    opts: &DrawOptions,
+) {
+    let padding = opts.label_padding();
+    let (x, y) = cursor;
+    let mut fields = vec![format!(
+        "{}, {}",
+        opts.display_length(x),
+        opts.display_length(y)
+    )];
+    if let Some((edges, corner_radius)) = measurement {
+        fields.push(measurement_text(edges, corner_radius, opts));
+    }
+    if let Some((format, rgb)) = color {
+        fields.push(format.format(rgb));
+    }
+    let text = fields.join("   |   ");
+
+    let height = opts.label_height();
+    let top = match position {
+        BarPosition::Top => 0.0,
+        BarPosition::Bottom => pixmap.height() as f32 - height,
+    };
+    if let Some(rect) = Rect::from_xywh(0.0, top, pixmap.width() as f32, height) {
+        let mut paint = Paint::default();
+        paint.set_color(opts.palette.label_bg);
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+    let baseline_y = top + padding.1 + opts.font_size() * 0.8;
+    match opts.font {
+        Some(font) => draw_text(pixmap, font, &text, CORNER_MARGIN, baseline_y, opts),
+        None => draw_fallback_digits(pixmap, &text, CORNER_MARGIN, baseline_y, opts),
+    }
+}
+
+/// Draw a text-entry prompt as a label beside the cursor
+pub fn draw_prompt(pixmap: &mut Pixmap, text: &str, cx: f32, cy: f32, opts: &DrawOptions) {
+    let (x, y) = get_label_position(cx, cy, pixmap.width(), pixmap.height());
+    draw_label(pixmap, text, x, y, opts);
+}
+
+/// Draw a centered panel listing `(key, description)` rows in two aligned columns
+pub fn draw_help(pixmap: &mut Pixmap, rows: &[(String, String)], opts: &DrawOptions) {
+    let key_width = rows
+        .iter()
+        .map(|(key, _)| opts.text_width(key))
+        .fold(0.0, f32::max);
+    let description_width = rows
+        .iter()
+        .map(|(_, description)| opts.text_width(description))
+        .fold(0.0, f32::max);
+
+    let line_height = opts.font_size() * HELP_LINE_SPACING;
+    let panel_width = key_width + HELP_COLUMN_GAP + description_width + HELP_PADDING * 2.0;
+    let panel_height = rows.len() as f32 * line_height + HELP_PADDING * 2.0;
+    let panel_x = (pixmap.width() as f32 - panel_width) / 2.0;
+    let panel_y = (pixmap.height() as f32 - panel_height) / 2.0;
+
+    draw_rounded_rect(
+        pixmap,
+        panel_x,
+        panel_y,
+        panel_width,
+        panel_height,
+        opts.label_radius() * 2.0,
+        opts.palette.label_bg,
+    );
+
+    let Some(font) = opts.font else {
+        return;
+    };
+    let key_x = panel_x + HELP_PADDING;
+    let description_x = key_x + key_width + HELP_COLUMN_GAP;
+    for (i, (key, description)) in rows.iter().enumerate() {
+        let baseline_y = panel_y + HELP_PADDING + i as f32 * line_height + opts.font_size() * 0.8;
+        draw_text(pixmap, font, key, key_x, baseline_y, opts);
+        draw_text(pixmap, font, description, description_x, baseline_y, opts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::embedded_font;
+    use std::path::PathBuf;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 200;
+
+    // Element spanning 60 x 40 pixels with the cursor inside it
+    const EDGES: Edges = Edges {
+        left: 20,
+        right: 79,
+        up: 30,
+        down: 69,
+    };
+    const CURSOR: (u32, u32) = (50, 50);
+
+    fn options(font: Option<&Font>) -> DrawOptions<'_> {
+        DrawOptions {
+            font,
+            font_size: DEFAULT_FONT_SIZE,
+            scale: 1.0,
+            palette: Palette::default(),
+            unit: Unit::Logical,
+            linear_blend: false,
+            antialias: true,
+            ruler: false,
+            round_sizes: false,
+            label_corner: None,
+            rem_base: 16.0,
+            extension_lines: false,
+            transposed: false,
+            target: None,
+            contrast_background: None,
+            status_bar: None,
+            pixels_per_mm: None,
+            real_unit: RealUnit::Millimeters,
+        }
+    }
+
+    fn pixmap() -> Pixmap {
+        Pixmap::new(WIDTH, HEIGHT).unwrap()
+    }
+
+    fn rgba(pixmap: &Pixmap, x: u32, y: u32) -> [u8; 4] {
+        let pixel = pixmap.pixel(x, y).unwrap().demultiply();
+        [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+    }
+
+    fn opaque(color: Color) -> [u8; 4] {
+        let color = color.to_color_u8();
+        [color.red(), color.green(), color.blue(), 255]
+    }
+
+    /// Compare against `tests/golden/<name>.png`, rewriting it when `UPDATE_GOLDEN` is set
+    fn assert_golden(name: &str, pixmap: &Pixmap) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", name));
+        let png = pixmap.encode_png().unwrap();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &png).unwrap();
+        }
+        // Round-trip through PNG so both sides went through the same demultiplication
+        let rendered = Pixmap::decode_png(&png).unwrap();
+        let golden = Pixmap::load_png(&path)
+            .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+        assert!(
+            rendered.data() == golden.data(),
+            "{} differs from {}",
+            name,
+            path.display()
+        );
+    }
+
+    #[test]
+    fn measurement_lines_run_between_edges() {
+        let mut pixmap = pixmap();
+        draw_measurements(
+            &mut pixmap,
+            &EDGES,
+            None,
+            None,
+            CURSOR.0,
+            CURSOR.1,
+            &options(None),
+        );
+        let line = opaque(Palette::default().line);
+
+        // Horizontal line along the cursor row, vertical along its column
+        assert_eq!(rgba(&pixmap, 35, CURSOR.1), line);
+        assert_eq!(rgba(&pixmap, 70, CURSOR.1), line);
+        assert_eq!(rgba(&pixmap, CURSOR.0, 35), line);
+        assert_eq!(rgba(&pixmap, CURSOR.0, 65), line);
+        // End caps cross the lines at the edges
+        assert_eq!(rgba(&pixmap, EDGES.left, CURSOR.1 - 6), line);
+        assert_eq!(rgba(&pixmap, CURSOR.0 - 6, EDGES.down), line);
+        // Nothing beyond them
+        assert_eq!(rgba(&pixmap, 10, CURSOR.1)[3], 0);
+        assert_eq!(rgba(&pixmap, CURSOR.0, 80)[3], 0);
+        assert_eq!(rgba(&pixmap, 35, 35)[3], 0);
+    }
+
+    #[test]
+    fn running_total_adds_inclusive_sizes() {
+        let mut total = RunningTotal::default();
+        total.add((10, 20, 39, 29));
+        total.add((100, 0, 109, 9));
+        assert_eq!(
+            total,
+            RunningTotal {
+                width: 40,
+                height: 20,
+                count: 2,
+            }
+        );
+        assert_eq!(
+            options(None).format_size(total.width, total.height),
+            "40 x 20"
+        );
+    }
+
+    #[test]
+    fn end_caps_show_edge_confidence() {
+        let mut pixmap = pixmap();
+        let strength = EdgeStrength {
+            left: 255,
+            right: 4,
+            up: 255,
+            down: 0,
+        };
+        draw_measurements(
+            &mut pixmap,
+            &EDGES,
+            None,
+            Some(&strength),
+            CURSOR.0,
+            CURSOR.1,
+            &options(None),
+        );
+        let palette = Palette::default();
+        assert_eq!(
+            rgba(&pixmap, EDGES.left, CURSOR.1 - 6),
+            opaque(palette.matched)
+        );
+        assert_eq!(
+            rgba(&pixmap, EDGES.right, CURSOR.1 - 6),
+            opaque(palette.warning)
+        );
+        assert_eq!(
+            rgba(&pixmap, CURSOR.0 - 6, EDGES.down),
+            opaque(palette.warning)
+        );
+        // The lines themselves keep the line color
+        assert_eq!(rgba(&pixmap, 35, CURSOR.1), opaque(palette.line));
+    }
+
+    #[test]
+    fn measurement_label_sits_below_right_of_cursor() {
+        let font = embedded_font().unwrap();
+        let opts = options(Some(&font));
+        let mut pixmap = pixmap();
+        draw_measurements(&mut pixmap, &EDGES, None, None, CURSOR.0, CURSOR.1, &opts);
+
+        let text = opts.format_size(60, 40);
+        assert_eq!(text, "60 x 40");
+        let (cx, cy) = (
+            (CURSOR.0 as f32 + LABEL_OFFSET.0) as u32,
+            (CURSOR.1 as f32 + LABEL_OFFSET.1) as u32,
+        );
+        let half_width = (opts.text_width(&text) / 2.0 + LABEL_PADDING.0) as u32;
+        let background = Palette::default().label_bg.to_color_u8();
+        // Inside the padding on both sides of the text, and just clear of the label
+        for x in [cx - half_width + 2, cx + half_width - 2] {
+            assert_eq!(rgba(&pixmap, x, cy)[3], background.alpha());
+        }
+        assert_eq!(rgba(&pixmap, cx - half_width - 2, cy)[3], 0);
+        assert_eq!(rgba(&pixmap, cx + half_width + 2, cy)[3], 0);
+    }
+
+    #[test]
+    fn label_without_font_draws_fallback_digits() {
+        let opts = options(None);
+        let mut pixmap = pixmap();
+        draw_label(&mut pixmap, "8", 100.0, 100.0, &opts);
+        let text = opts.palette.label_text;
+
+        // The 8's middle segment runs across the glyph halfway up it, from the label's
+        // left padding (see `draw_label`)
+        let size = opts.font_size();
+        let glyph_left = 100.0 - opts.text_width("8") / 2.0;
+        let baseline = 100.0 - opts.label_height() / 2.0 + opts.label_padding().1 + size * 0.8;
+        let middle = baseline - size * FALLBACK_GLYPH_SIZE.0 / 2.0;
+        let center = glyph_left + size * FALLBACK_GLYPH_SIZE.1 / 2.0;
+        assert_eq!(
+            rgba(&pixmap, center as u32, middle as u32),
+            [text.red(), text.green(), text.blue(), 255]
+        );
+        // Its centers above and below the middle segment are open
+        let open = rgba(&pixmap, center as u32, (middle - size * 0.15) as u32);
+        assert_ne!(open, [text.red(), text.green(), text.blue(), 255]);
+    }
+
+    #[test]
+    fn label_flips_near_screen_edges() {
+        let (x, y) = get_label_position(WIDTH as f32 - 10.0, HEIGHT as f32 - 10.0, WIDTH, HEIGHT);
+        assert!(x < WIDTH as f32 - 10.0);
+        assert!(y < HEIGHT as f32 - 10.0);
+    }
+
+    #[test]
+    fn round_length_snaps_within_tolerance() {
+        assert_eq!(round_length(119), 120);
+        assert_eq!(round_length(118), 120);
+        assert_eq!(round_length(116), 115);
+        assert_eq!(round_length(113), 113);
+        assert_eq!(round_length(117), 117);
+        assert_eq!(round_length(2), 2);
+    }
+
+    #[test]
+    fn rounded_size_is_marked() {
+        let mut opts = options(None);
+        assert_eq!(opts.format_rounded_size(119, 41), None);
+        opts.round_sizes = true;
+        assert_eq!(
+            opts.format_rounded_size(119, 41),
+            Some("≈ 120 x 40".to_string())
+        );
+        assert_eq!(opts.format_rounded_size(120, 40), None);
+    }
+
+    #[test]
+    fn real_size_follows_monitor_density() {
+        let mut opts = options(None);
+        assert_eq!(opts.format_real_size(96, 48), None);
+        // 96 DPI
+        opts.pixels_per_mm = Some((96.0 / MM_PER_INCH, 96.0 / MM_PER_INCH));
+        assert_eq!(
+            opts.format_real_size(96, 48),
+            Some("25.4 x 12.7mm".to_string())
+        );
+        opts.real_unit = RealUnit::Inches;
+        assert_eq!(
+            opts.format_real_size(96, 48),
+            Some("1.00 x 0.50in".to_string())
+        );
+    }
+
+    #[test]
+    fn crosshair_is_centered_on_cursor() {
+        let mut pixmap = pixmap();
+        let palette = Palette::default();
+        draw_crosshair(
+            &mut pixmap,
+            100.0,
+            100.0,
+            CrosshairStyle::Cross,
+            &palette,
+            true,
+        );
+        let line = opaque(palette.line);
+        assert_eq!(rgba(&pixmap, 90, 100)[..3], line[..3]);
+        assert_eq!(rgba(&pixmap, 100, 110)[..3], line[..3]);
+        assert_eq!(rgba(&pixmap, 90, 90)[3], 0);
+        assert_eq!(rgba(&pixmap, 130, 100)[3], 0);
+    }
+
+    #[test]
+    fn crisp_crosshair_has_hard_edges() {
+        let mut pixmap = pixmap();
+        let palette = Palette::default();
+        draw_crosshair(
+            &mut pixmap,
+            100.5,
+            100.5,
+            CrosshairStyle::Cross,
+            &palette,
+            false,
+        );
+        assert!(
+            pixmap
+                .pixels()
+                .iter()
+                .all(|p| p.alpha() == 0 || p.alpha() == 255)
+        );
+    }
+
+    #[test]
+    fn measurements_match_golden() {
+        let font = embedded_font().unwrap();
+        let opts = options(Some(&font));
+        let mut pixmap = pixmap();
+        draw_measurements(
+            &mut pixmap,
+            &EDGES,
+            Some(4),
+            None,
+            CURSOR.0,
+            CURSOR.1,
+            &opts,
+        );
+        draw_crosshair(
+            &mut pixmap,
+            CURSOR.0 as f32,
+            CURSOR.1 as f32,
+            CrosshairStyle::Cross,
+            &opts.palette,
+            opts.antialias,
+        );
+        assert_golden("measurements", &pixmap);
+    }
 
-    if let Some(font) = font {
-        let text_x = label_x + LABEL_PADDING.0;
-        let baseline_y = label_y + LABEL_PADDING.1 + FONT_SIZE * 0.8;
-        draw_text(pixmap, font, text, text_x, baseline_y);
+    #[test]
+    fn rectangle_measurement_matches_golden() {
+        let font = embedded_font().unwrap();
+        let opts = options(Some(&font));
+        let mut pixmap = pixmap();
+        draw_rectangle_measurement(&mut pixmap, 40, 40, 199, 119, false, &opts);
+        assert_golden("rectangle", &pixmap);
     }
 }
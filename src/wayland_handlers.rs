@@ -1,12 +1,41 @@
-use crate::capture::Screenshot;
-use crate::edge_detection::{find_edges, snap_edge_x, snap_edge_y};
-use crate::ui::{draw_crosshair, draw_measurements, draw_rectangle_measurement};
-use std::process::Command;
+use crate::capture::{
+    Screenshot, capture_screen, get_active_window_geometry, linear_to_srgb, load_image,
+    srgb_to_linear,
+};
+use crate::clipboard;
+use crate::config::{Config, Mode};
+use crate::diff::{Diff, diff};
+use crate::edge_detection::{
+    EdgeSmoother, ScanBounds, ScanOptions, edge_mask, edge_report, edge_strength,
+    estimate_corner_radius, find_border_box, find_container, find_content_bounds, find_edges,
+    find_gaps, find_run_edges, find_text_lines, snap_edge_x, snap_edge_y,
+};
+use crate::font::{Font, load_font};
+use crate::keybinds::{
+    Action, BINDINGS, CTRL_BINDINGS, OTHER_BINDINGS, action_for, arrow_direction, key_name,
+};
+use crate::notify::notify;
+use crate::shell::{self, OverlayShell, OverlaySurface};
+use crate::state::State;
+use crate::ui::{
+    ColorFormat, Contrast, Corner, CrosshairStyle, DEFAULT_FONT_SIZE, DrawOptions, MM_PER_INCH,
+    Palette, RunningTotal, Unit, draw_baselines, draw_border_box, draw_color_readout,
+    draw_crosshair, draw_diff, draw_edge_map, draw_element_outline, draw_gaps, draw_help,
+    draw_loupe, draw_margins, draw_measurements, draw_misalignments, draw_path, draw_pin_delta,
+    draw_prompt, draw_radial_measurement, draw_rectangle_measurement, draw_running_total,
+    draw_scan_bounds, draw_screen_size, draw_status_bar, draw_window_origin,
+};
+use rustix::{fs::OFlags, io::Errno};
+use std::io::Write;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use smithay_client_toolkit::{
-    compositor::{CompositorHandler, CompositorState},
+    compositor::{CompositorHandler, CompositorState, Region},
     delegate_compositor, delegate_keyboard, delegate_layer, delegate_output, delegate_pointer,
-    delegate_registry, delegate_seat, delegate_shm,
+    delegate_pointer_constraints, delegate_registry, delegate_relative_pointer, delegate_seat,
+    delegate_shm, delegate_xdg_shell, delegate_xdg_window,
     output::{OutputHandler, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
@@ -16,15 +45,17 @@ use smithay_client_toolkit::{
         pointer::{
             PointerEvent, PointerEventKind, PointerHandler, cursor_shape::CursorShapeManager,
         },
+        pointer_constraints::{PointerConstraintsHandler, PointerConstraintsState},
+        relative_pointer::{RelativeMotionEvent, RelativePointerHandler, RelativePointerState},
     },
     shell::{
-        WaylandSurface,
-        wlr_layer::{
-            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
-            LayerSurfaceConfigure,
-        },
+        wlr_layer::{LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
+        xdg::window::{Window, WindowConfigure, WindowHandler},
+    },
+    shm::{
+        Shm, ShmHandler,
+        slot::{Buffer, SlotPool},
     },
-    shm::{Shm, ShmHandler, slot::SlotPool},
 };
 use tiny_skia::Pixmap;
 use wayland_client::{
@@ -39,35 +70,65 @@ use wayland_protocols::wp::fractional_scale::v1::client::{
     wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
     wp_fractional_scale_v1::{self, WpFractionalScaleV1},
 };
+use wayland_protocols::wp::pointer_constraints::zv1::client::{
+    zwp_confined_pointer_v1::ZwpConfinedPointerV1, zwp_locked_pointer_v1::ZwpLockedPointerV1,
+    zwp_pointer_constraints_v1::Lifetime,
+};
+use wayland_protocols::wp::relative_pointer::zv1::client::zwp_relative_pointer_v1::ZwpRelativePointerV1;
 use wayland_protocols::wp::viewporter::client::{
     wp_viewport::WpViewport, wp_viewporter::WpViewporter,
 };
 
-fn find_system_font() -> Option<Vec<u8>> {
-    let output = Command::new("fc-match")
-        .args(["-f", "%{file}", "sans-serif"])
-        .output()
-        .ok()?;
-    let path = String::from_utf8(output.stdout).ok()?;
-    std::fs::read(path.trim()).ok()
-}
+// Size of the window sampled to pick a high-contrast palette
+const CONTRAST_SAMPLE_RADIUS: u32 = 20;
+
+// Inclusive `(x1, y1, x2, y2)` pixel bounds of a region of the overlay buffer
+type Bounds = (u32, u32, u32, u32);
+
+// Physical pixels the measurement point moves per unit of relative motion in precision mode
+const PRECISION_FACTOR: f64 = 0.1;
+
+// Loupe magnification: the default and the range scrolling doubles or halves it within
+const LOUPE_ZOOM: u32 = 8;
+const LOUPE_ZOOM_RANGE: (u32, u32) = (2, 32);
+
+// Label text size change per Ctrl+= / Ctrl+- press, and its bounds
+const FONT_SIZE_STEP: u32 = 2;
+const FONT_SIZE_RANGE: (u32, u32) = (12, 64);
+
+// Logical pixels an arrow key moves the drawn rectangle with Shift held
+const NUDGE_STEP_LARGE: f64 = 10.0;
+
+// Logical distance from a drawn rectangle's side within which pressing grabs it to resize
+const RESIZE_HANDLE_REACH: f64 = 6.0;
+
+// Logical extent of the cursor painted over with --mask-cursor: right and down from the
+// pointer position, and the margin up and left of it
+const CURSOR_MASK_SIZE: f64 = 32.0;
+const CURSOR_MASK_MARGIN: f64 = 4.0;
+
+// Buffers kept around so one can be drawn while the compositor still holds another
+const BUFFER_COUNT: usize = 2;
 
 pub struct WaylandApp {
+    config: Config,
+
     // Wayland protocol state
     registry_state: RegistryState,
     seat_state: SeatState,
     output_state: OutputState,
     compositor_state: CompositorState,
     shm: Shm,
-    layer_shell: LayerShell,
+    shell: Box<dyn OverlayShell>,
 
     // Overlay surface
-    layer_surface: Option<LayerSurface>,
+    overlay_surface: Option<Box<dyn OverlaySurface>>,
     pool: Option<SlotPool>,
     width: u32,
     height: u32,
     scale: f64,
     target_output_name: Option<String>,
+    transform: u32,
 
     // Fractional scaling support
     fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
@@ -78,101 +139,450 @@ pub struct WaylandApp {
     // Cursor
     cursor_shape_manager: Option<CursorShapeManager>,
     cursor_shape_device: Option<WpCursorShapeDeviceV1>,
+    pointer_constraints: PointerConstraintsState,
+    relative_pointer_state: RelativePointerState,
+    pointer: Option<wl_pointer::WlPointer>,
+    relative_pointer: Option<ZwpRelativePointerV1>,
+    // Pointer lock held while in precision mode, where relative motion moves the point
+    precision_lock: Option<ZwpLockedPointerV1>,
 
     // Core app state
     pointer_x: f64,
     pointer_y: f64,
-    font: Option<fontdue::Font>,
+    font: Option<Font>,
+    contrast: Contrast,
+    unit: Unit,
     needs_redraw: bool,
+    // Set from requesting a frame callback until it is done, so further redraws wait for it
+    frame_pending: bool,
+    // Set while pointer motion is the only change since the last frame
+    motion_only: bool,
+    // Cursor pixel measured in the last frame, `None` while the pointer was off the overlay
+    drawn_cursor: Option<(u32, u32)>,
     cached_pixmap: Option<Pixmap>,
+    // Bounds of the overlay in the last committed frame, for damage tracking
+    overlay_bounds: Option<Bounds>,
+    // Buffers drawn into in turn, each with the region where it differs from the background
+    buffers: Vec<(Buffer, Option<Bounds>)>,
+    // Fully transparent buffer shown while live captures are taken, keeping the surface mapped
+    blank_buffer: Option<Buffer>,
+    // Set when the screenshot is replaced, so every buffer restores the new background
+    background_changed: bool,
     screenshot: Screenshot,
 
     // Drag-to-measure state
     drag_start: Option<(f64, f64)>,
     drag_rect: Option<(u32, u32, u32, u32)>,
+    // Sides of `drag_rect` being dragged to resize it, which then follow the pointer
+    resizing: Option<ResizeHandle>,
+    // Serial of the last pointer enter, needed to change the cursor shape
+    enter_serial: u32,
+    cursor_shape: wp_cursor_shape_device_v1::Shape,
+    // Free-hand path in logical coordinates, recorded while dragging in path mode
+    path_mode: bool,
+    // Center in logical coordinates that radius and angle to the cursor are measured from
+    radial_center: Option<(f64, f64)>,
+    // Logical pointer position at capture time, painted over once the scale is known
+    // (with --mask-cursor)
+    unmasked_cursor: Option<(f64, f64)>,
+    // Set until the first pointer enter, which tells where the cursor was at startup
+    mask_on_enter: bool,
+    // Point in logical coordinates the cursor's movement is measured from
+    pin: Option<(f64, f64)>,
+    // Last measurement written to --watch-file
+    watched_geometry: Option<String>,
+    path: Vec<(f64, f64)>,
+    // Earlier rectangles kept on screen by starting a new drag with Shift held
+    pinned_rects: Vec<(u32, u32, u32, u32)>,
     is_dragging: bool,
+    modifiers: Modifiers,
+    // Whether the overlay currently takes pointer input (always, unless --click-through)
+    input_captured: bool,
+    grid_snap: bool,
+    show_help: bool,
+    linear_blend: bool,
+    antialias: bool,
+    ruler: bool,
+    round_sizes: bool,
+    label_corner: Option<Corner>,
+    show_gaps: bool,
+    show_margins: bool,
+    show_baselines: bool,
+    show_border_box: bool,
+    /// Measure the run of pixels the cursor is on instead of the span between edges
+    run_width: bool,
+    show_screen_size: bool,
+    show_content_bounds: bool,
+    // Bounding box of the current screenshot's content, computed when first shown; holds
+    // `None` when the screen is all background
+    content_bounds: Option<Option<Bounds>>,
+    // Summed physical widths and heights of measurements so far, and how many, while
+    // adding them up
+    running_total: Option<RunningTotal>,
+    // Edge map of the current screenshot, computed when first shown
+    edge_map: Option<Vec<bool>>,
+    // Earlier capture given with `--compare`, and what has changed since
+    reference: Option<Pixmap>,
+    diff: Option<Diff>,
+    show_edge_map: bool,
+    // Region edge detection is confined to while the cursor is inside it
+    scan_bounds: Option<ScanBounds>,
+    smoothing: bool,
+    edge_confidence: bool,
+    crosshair: CrosshairStyle,
+    mode: Mode,
+    // Physical point inside the element stepped to with Tab, measured instead of the cursor
+    selected_element: Option<(u32, u32)>,
+    // Levels of enclosing container measured instead of the element, stepped with [ and ]
+    container_depth: usize,
+    extension_lines: bool,
+    // Report sizes and `--geometry` in the output's native panel orientation
+    native_orientation: bool,
+    // Label text size in pixels, changed with Ctrl+= / Ctrl+-
+    font_size: u32,
+    // Size being typed as `WxH` while entry is active
+    size_entry: Option<String>,
+    // Typed size in logical pixels, drawn as a rectangle hanging from the cursor
+    typed_size: Option<(u32, u32)>,
+    // Whether the pointer is over the overlay, between Enter and Leave
+    pointer_inside: bool,
+    // Ignore pointer motion, freezing the measurement point
+    pointer_locked: bool,
+    loupe: bool,
+    loupe_zoom: u32,
+    // Format listed first in the color readout, if it is shown
+    color_readout: Option<ColorFormat>,
+    // Focused window `(x, y, width, height)` in logical pixels when measuring relative to it
+    window_origin: Option<(i32, i32, i32, i32)>,
+    edge_smoother: EdgeSmoother,
 
     // Control
+    last_activity: Instant,
     exit: bool,
 }
 
+/// Sides of a drawn rectangle that a resize drag moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ResizeHandle {
+    left: bool,
+    right: bool,
+    top: bool,
+    bottom: bool,
+}
+
+impl ResizeHandle {
+    /// Cursor shown over the handle, pointing the ways it resizes
+    fn cursor_shape(self) -> wp_cursor_shape_device_v1::Shape {
+        use wp_cursor_shape_device_v1::Shape;
+        match (self.left || self.right, self.top || self.bottom) {
+            (true, true) if self.left == self.top => Shape::NwseResize,
+            (true, true) => Shape::NeswResize,
+            (true, false) => Shape::EwResize,
+            _ => Shape::NsResize,
+        }
+    }
+}
+
+/// Handle of the inclusive physical `rect` at (x, y): the sides within `reach` physical
+/// pixels of it, preferring the nearer one where a small rectangle's sides are both close
+fn resize_handle(rect: Bounds, x: u32, y: u32, reach: u32) -> Option<ResizeHandle> {
+    let (x1, y1, x2, y2) = rect;
+    if x + reach < x1 || x > x2 + reach || y + reach < y1 || y > y2 + reach {
+        return None;
+    }
+    // Which of the sides `low` and `high` along one axis are grabbed at `p`
+    let grab = |p: u32, low: u32, high: u32| {
+        let (to_low, to_high) = (p.abs_diff(low), p.abs_diff(high));
+        if to_low.min(to_high) > reach {
+            (false, false)
+        } else {
+            (to_low <= to_high, to_low > to_high)
+        }
+    };
+    let (left, right) = grab(x, x1, x2);
+    let (top, bottom) = grab(y, y1, y2);
+    (left || right || top || bottom).then_some(ResizeHandle {
+        left,
+        right,
+        top,
+        bottom,
+    })
+}
+
 fn normalize_rect(x1: u32, y1: u32, x2: u32, y2: u32) -> (u32, u32, u32, u32) {
     (x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2))
 }
 
-fn to_physical(logical: f64, scale: f64) -> u32 {
-    (logical * scale) as u32
+/// Grow optional bounds to also cover `rect`
+fn union_rect(bounds: Option<Bounds>, rect: Bounds) -> Bounds {
+    match bounds {
+        Some((x1, y1, x2, y2)) => (
+            x1.min(rect.0),
+            y1.min(rect.1),
+            x2.max(rect.2),
+            y2.max(rect.3),
+        ),
+        None => rect,
+    }
+}
+
+/// Map a logical pointer position to the physical pixel it is nearest to. Rounding (rather
+/// than truncating) keeps fractional scales from biasing every measurement towards the
+/// top-left; all pointer-to-physical conversions go through here. The result is clamped
+/// to the last of the `size` pixels along the axis, which rounding the final half logical
+/// pixel would otherwise overshoot.
+fn to_physical(logical: f64, scale: f64, size: u32) -> u32 {
+    ((logical * scale).round() as u32).min(size.saturating_sub(1))
+}
+
+/// Pick an overlay buffer format the compositor advertises, preferring ARGB8888
+fn choose_buffer_format(formats: &[wl_shm::Format]) -> wl_shm::Format {
+    [
+        wl_shm::Format::Argb8888,
+        wl_shm::Format::Xrgb8888,
+        wl_shm::Format::Abgr8888,
+        wl_shm::Format::Xbgr8888,
+    ]
+    .into_iter()
+    .find(|f| formats.contains(f))
+    .unwrap_or(wl_shm::Format::Argb8888)
+}
+
+/// Round a physical coordinate to the nearest multiple of a logical grid size
+fn snap_to_grid(phys: u32, grid_size: u32, scale: f64) -> u32 {
+    let step = grid_size as f64 * scale;
+    ((phys as f64 / step).round() * step).round() as u32
+}
+
+/// Map an inclusive physical rect on a `width` x `height` output with wl_output `transform`
+/// into the panel's native (unrotated, unflipped) coordinates
+fn native_rect(rect: Bounds, width: u32, height: u32, transform: u32) -> Bounds {
+    let native = |x: u32, y: u32| {
+        let x = if transform >= 4 { width - 1 - x } else { x };
+        match transform % 4 {
+            1 => (y, width - 1 - x),
+            2 => (width - 1 - x, height - 1 - y),
+            3 => (height - 1 - y, x),
+            _ => (x, y),
+        }
+    };
+    let (x1, y1) = native(rect.0, rect.1);
+    let (x2, y2) = native(rect.2, rect.3);
+    normalize_rect(x1, y1, x2, y2)
+}
+
+/// Replace the contents of `path` with `line` without readers ever seeing a partial write:
+/// regular files are written beside it and renamed over it, while named pipes get the line
+/// only when a reader has them open, so the overlay never blocks on one
+fn write_watch_file(path: &Path, line: &str) -> std::io::Result<()> {
+    if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo()) {
+        // Opening fails with ENXIO while no reader has the pipe open, and a write would
+        // block while its buffer is full; either way this change is skipped
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(OFlags::NONBLOCK.bits() as i32)
+            .open(path)
+            .and_then(|mut pipe| writeln!(pipe, "{}", line));
+        return match written {
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.raw_os_error() == Some(Errno::NXIO.raw_os_error()) =>
+            {
+                Ok(())
+            }
+            result => result,
+        };
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, format!("{}\n", line))?;
+    std::fs::rename(&tmp, path)
 }
 
 impl WaylandApp {
     pub fn new(
         conn: &Connection,
+        config: Config,
         screenshot: Screenshot,
+        reference: Option<Pixmap>,
         target_output_name: Option<String>,
+        transform: u32,
     ) -> (Self, EventQueue<Self>) {
         let (globals, event_queue) = registry_queue_init(conn).expect("Failed to init registry");
         let qh = event_queue.handle();
 
         let compositor_state =
             CompositorState::bind(&globals, &qh).expect("wl_compositor not available");
-        let layer_shell = LayerShell::bind(&globals, &qh).expect("layer shell not available");
+        let shell =
+            shell::bind(&globals, &qh).expect("Neither layer shell nor xdg shell available");
         let shm = Shm::bind(&globals, &qh).expect("wl_shm not available");
         let seat_state = SeatState::new(&globals, &qh);
         let output_state = OutputState::new(&globals, &qh);
         let registry_state = RegistryState::new(&globals);
         let cursor_shape_manager = CursorShapeManager::bind(&globals, &qh).ok();
+        let pointer_constraints = PointerConstraintsState::bind(&globals, &qh);
+        let relative_pointer_state = RelativePointerState::bind(&globals, &qh);
 
         let fractional_scale_manager: Option<WpFractionalScaleManagerV1> =
             globals.bind(&qh, 1..=1, ()).ok();
         let viewporter: Option<WpViewporter> = globals.bind(&qh, 1..=1, ()).ok();
 
-        let font = find_system_font().and_then(|data| {
-            fontdue::Font::from_bytes(data, fontdue::FontSettings::default()).ok()
-        });
+        let font = load_font();
+        // Pick up where the last run left off; explicit options win over remembered state
+        let state = State::load();
+        let crosshair = config.crosshair.unwrap_or(state.crosshair);
+        let mode = config.mode.unwrap_or(state.mode);
+        let mask_on_enter = config.mask_cursor;
+        let diff = reference
+            .as_ref()
+            .map(|reference| diff(&screenshot, reference));
 
         let app = Self {
+            config,
             registry_state,
             seat_state,
             output_state,
             compositor_state,
             shm,
-            layer_shell,
-            layer_surface: None,
+            shell,
+            overlay_surface: None,
             pool: None,
             width: 0,
             height: 0,
             scale: 1.0,
             target_output_name,
+            transform,
             fractional_scale_manager,
             fractional_scale: None,
             viewporter,
             viewport: None,
             cursor_shape_manager,
             cursor_shape_device: None,
+            pointer_constraints,
+            relative_pointer_state,
+            pointer: None,
+            relative_pointer: None,
+            precision_lock: None,
             pointer_x: 0.0,
             pointer_y: 0.0,
             font,
+            contrast: state.contrast,
+            unit: state.unit,
             needs_redraw: true,
+            frame_pending: false,
+            motion_only: false,
+            drawn_cursor: None,
             cached_pixmap: None,
+            overlay_bounds: None,
+            buffers: Vec::new(),
+            blank_buffer: None,
+            background_changed: false,
             screenshot,
             drag_start: None,
             drag_rect: None,
+            resizing: None,
+            enter_serial: 0,
+            cursor_shape: wp_cursor_shape_device_v1::Shape::Crosshair,
+            path_mode: false,
+            radial_center: None,
+            unmasked_cursor: None,
+            mask_on_enter,
+            pin: None,
+            watched_geometry: None,
+            path: Vec::new(),
+            pinned_rects: Vec::new(),
             is_dragging: false,
+            modifiers: Modifiers::default(),
+            input_captured: true,
+            grid_snap: state.grid_snap,
+            show_help: false,
+            linear_blend: state.linear_blend,
+            antialias: !state.crisp_lines,
+            ruler: state.ruler,
+            round_sizes: state.round_sizes,
+            label_corner: state.label_corner,
+            show_gaps: state.show_gaps,
+            show_margins: state.show_margins,
+            show_baselines: state.show_baselines,
+            show_border_box: state.show_border_box,
+            run_width: state.run_width,
+            show_screen_size: false,
+            show_content_bounds: false,
+            content_bounds: None,
+            running_total: None,
+            edge_map: None,
+            reference,
+            diff,
+            show_edge_map: false,
+            scan_bounds: None,
+            smoothing: state.smoothing,
+            edge_confidence: state.edge_confidence,
+            crosshair,
+            mode,
+            selected_element: None,
+            container_depth: 0,
+            extension_lines: state.extension_lines,
+            native_orientation: state.native_orientation,
+            font_size: state
+                .font_size
+                .unwrap_or(DEFAULT_FONT_SIZE)
+                .clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1),
+            size_entry: None,
+            typed_size: None,
+            pointer_inside: false,
+            pointer_locked: false,
+            loupe: state.loupe,
+            loupe_zoom: LOUPE_ZOOM,
+            color_readout: state.color_readout,
+            window_origin: None,
+            edge_smoother: EdgeSmoother::default(),
+            last_activity: Instant::now(),
             exit: false,
         };
 
         (app, event_queue)
     }
 
+    /// Size of the captured output in logical pixels: as the compositor reports it, else the
+    /// capture divided by the output's scale
+    fn output_logical_size(&self) -> (u32, u32) {
+        let info = self.target_output_name.as_ref().and_then(|name| {
+            self.output_state
+                .outputs()
+                .filter_map(|o| self.output_state.info(&o))
+                .find(|i| i.name.as_deref() == Some(name))
+        });
+        if let Some((width, height)) = info.as_ref().and_then(|i| i.logical_size)
+            && width > 0
+            && height > 0
+        {
+            return (width as u32, height as u32);
+        }
+        let scale = info.map_or(self.scale, |i| i.scale_factor as f64);
+        let logical = |physical: u32| (physical as f64 / scale).round() as u32;
+        (
+            logical(self.screenshot.width),
+            logical(self.screenshot.height),
+        )
+    }
+
     pub fn create_surface(&mut self, qh: &QueueHandle<Self>) {
-        // Find the target output by name using OutputState
+        // Find the target output by name using OutputState, so the overlay covers exactly
+        // the captured output rather than whichever one the compositor picks
         let target_output = self.target_output_name.as_ref().and_then(|name| {
-            self.output_state.outputs().find(|o| {
+            let output = self.output_state.outputs().find(|o| {
                 self.output_state
                     .info(o)
                     .map(|i| i.name.as_deref() == Some(name))
                     .unwrap_or(false)
-            })
+            });
+            if output.is_none() {
+                eprintln!(
+                    "hypruler: output {} not found, the overlay may appear elsewhere",
+                    name
+                );
+            }
+            output
         });
 
         let surface = self.compositor_state.create_surface(qh);
@@ -187,69 +597,886 @@ impl WaylandApp {
             self.viewport = Some(viewporter.get_viewport(&surface, qh, ()));
         }
 
-        let layer_surface = self.layer_shell.create_layer_surface(
-            qh,
-            surface,
-            Layer::Overlay,
-            Some("hypruler"),
-            target_output.as_ref(),
+        self.overlay_surface = Some(
+            self.shell
+                .create_overlay(qh, surface, target_output.as_ref()),
         );
+        // Keyboard input stays exclusive so holding Ctrl can still be seen
+        if self.config.click_through {
+            self.set_input_captured(false);
+        }
+        if let Some(overlay_surface) = &self.overlay_surface {
+            overlay_surface.commit();
+        }
+    }
 
-        layer_surface.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
-        layer_surface.set_exclusive_zone(-1);
-        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
-        layer_surface.commit();
-
-        self.layer_surface = Some(layer_surface);
+    /// Switch between taking pointer input and passing it through to the windows below,
+    /// via an empty input region. The change applies on the next surface commit.
+    fn set_input_captured(&mut self, captured: bool) {
+        let Some(overlay_surface) = &self.overlay_surface else {
+            return;
+        };
+        if captured {
+            overlay_surface.wl_surface().set_input_region(None);
+        } else if let Ok(region) = Region::new(&self.compositor_state) {
+            // The region is copied by the request, so it can be dropped straight away
+            overlay_surface
+                .wl_surface()
+                .set_input_region(Some(region.wl_region()));
+        }
+        self.input_captured = captured;
     }
 
     pub fn should_exit(&self) -> bool {
         self.exit
     }
 
-    fn draw(&mut self, _qh: &QueueHandle<Self>) {
-        if self.layer_surface.is_none() || self.pool.is_none() {
-            return;
+    pub fn request_exit(&mut self) {
+        self.exit = true;
+    }
+
+    /// Physical pixels per millimeter across and down the capture, from `--dpi` or
+    /// `--monitor-mm`
+    fn pixels_per_mm(&self) -> Option<(f64, f64)> {
+        if let Some(dpi) = self.config.dpi {
+            return Some((dpi / MM_PER_INCH, dpi / MM_PER_INCH));
         }
-        if self.width == 0 || self.height == 0 || !self.needs_redraw {
+        let monitor = self.config.monitor_mm?;
+        Some((
+            self.screenshot.width as f64 / monitor.width,
+            self.screenshot.height as f64 / monitor.height,
+        ))
+    }
+
+    /// Paint over the cursor in the capture at logical (x, y), for compositors that leave it in
+    /// screencopy frames. Hotspots are at or near the cursor image's top-left.
+    fn mask_cursor(&mut self, x: f64, y: f64) {
+        let phys = |v: f64| (v.max(0.0) * self.scale).round() as u32;
+        let (x1, y1) = (phys(x - CURSOR_MASK_MARGIN), phys(y - CURSOR_MASK_MARGIN));
+        let (x2, y2) = (phys(x + CURSOR_MASK_SIZE), phys(y + CURSOR_MASK_SIZE));
+        self.screenshot.mask_region(x1, y1, x2, y2);
+        self.background_changed = true;
+        self.edge_map = None;
+        self.content_bounds = None;
+    }
+
+    /// Edge detection options, with `--min-size` in physical pixels
+    fn scan_options(&self) -> ScanOptions {
+        self.config.scan_options(self.scale)
+    }
+
+    /// Physical bounds of the drawn rectangle, or of the detected edge box around the cursor
+    fn selection(&self) -> (u32, u32, u32, u32) {
+        self.drag_rect.unwrap_or_else(|| {
+            let x = to_physical(self.pointer_x, self.scale, self.screenshot.width);
+            let y = to_physical(self.pointer_y, self.scale, self.screenshot.height);
+            let edges = if self.run_width {
+                find_run_edges(&self.screenshot, x, y)
+            } else {
+                find_edges(
+                    &self.screenshot,
+                    x,
+                    y,
+                    self.scan_bounds,
+                    &self.scan_options(),
+                )
+            };
+            (edges.left, edges.up, edges.right, edges.down)
+        })
+    }
+
+    /// Show the final selection's size and geometry in a desktop notification, with a
+    /// thumbnail of the selected pixels so the result outlives the overlay
+    pub fn notify(&self) {
+        let (x1, y1, x2, y2) = self.selection();
+        let logical = |phys: u32| (phys as f64 / self.scale).round() as u32;
+        let summary = format!("{} x {}", logical(x2 - x1 + 1), logical(y2 - y1 + 1));
+
+        // The daemon may read the image after we exit, so it goes somewhere that persists.
+        // Only the private runtime dir will do: a fixed name in a shared one like /tmp could
+        // be a symlink planted by another user, so without it the thumbnail is skipped.
+        let path = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("hypruler-notify.png"));
+        let thumbnail = path.as_deref().filter(|path| {
+            let saved = self
+                .screenshot
+                .crop_png(x1, y1, x2, y2)
+                .and_then(|png| std::fs::write(path, png).map_err(|e| e.to_string()));
+            if let Err(e) = &saved {
+                eprintln!("hypruler: failed to save notification thumbnail: {}", e);
+            }
+            saved.is_ok()
+        });
+
+        if let Err(e) = notify(&summary, &self.geometry(), thumbnail) {
+            eprintln!("hypruler: {}", e);
+        }
+    }
+
+    /// Current selection as an ImageMagick/slurp-style `WxH+X+Y` geometry in logical pixels.
+    /// Uses the drawn rectangle, or the detected edge box around the cursor if there is none.
+    /// Offsets are relative to the focused window while window-relative mode is on.
+    pub fn geometry(&self) -> String {
+        self.format_geometry(self.selection())
+    }
+
+    /// Physical bounds `(x1, y1, x2, y2)` as a `WxH+X+Y` geometry in logical pixels
+    fn format_geometry(&self, (x1, y1, x2, y2): Bounds) -> String {
+        let logical = |phys: u32| (phys as f64 / self.scale).round() as i32;
+        let (origin_x, origin_y) = self.window_origin.map_or((0, 0), |(x, y, _, _)| (x, y));
+        if self.native_orientation {
+            let (width, height) = (self.screenshot.width, self.screenshot.height);
+            let native = |rect| native_rect(rect, width, height, self.transform);
+            let (x1, y1, x2, y2) = native((x1, y1, x2, y2));
+            // The window's top-left in native coordinates is whichever corner maps there
+            let (origin_x, origin_y) = self.window_origin.map_or((0, 0), |(x, y, w, h)| {
+                let phys = |v: i32, size| to_physical(v.max(0) as f64, self.scale, size);
+                let (x1, y1, _, _) = native((
+                    phys(x, width),
+                    phys(y, height),
+                    phys(x + w - 1, width),
+                    phys(y + h - 1, height),
+                ));
+                (logical(x1), logical(y1))
+            });
+            return format!(
+                "{}x{}{:+}{:+}",
+                logical(x2 - x1 + 1),
+                logical(y2 - y1 + 1),
+                logical(x1) - origin_x,
+                logical(y1) - origin_y
+            );
+        }
+        format!(
+            "{}x{}{:+}{:+}",
+            logical(x2 - x1 + 1),
+            logical(y2 - y1 + 1),
+            logical(x1) - origin_x,
+            logical(y1) - origin_y
+        )
+    }
+
+    /// Toggles to remember for the next run
+    pub fn state(&self) -> State {
+        State {
+            contrast: self.contrast,
+            unit: self.unit,
+            grid_snap: self.grid_snap,
+            linear_blend: self.linear_blend,
+            crisp_lines: !self.antialias,
+            ruler: self.ruler,
+            round_sizes: self.round_sizes,
+            label_corner: self.label_corner,
+            show_gaps: self.show_gaps,
+            show_margins: self.show_margins,
+            show_baselines: self.show_baselines,
+            show_border_box: self.show_border_box,
+            run_width: self.run_width,
+            smoothing: self.smoothing,
+            edge_confidence: self.edge_confidence,
+            crosshair: self.crosshair,
+            mode: self.mode,
+            extension_lines: self.extension_lines,
+            color_readout: self.color_readout,
+            loupe: self.loupe,
+            native_orientation: self.native_orientation,
+            font_size: (self.font_size != DEFAULT_FONT_SIZE).then_some(self.font_size),
+        }
+    }
+
+    /// Time since the last pointer or keyboard input
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Mark the overlay dirty and request a frame callback to redraw it, unless one is
+    /// already pending: however many events arrive in between, the overlay is drawn at
+    /// most once per frame
+    fn request_redraw(&mut self, qh: &QueueHandle<Self>) {
+        self.needs_redraw = true;
+        self.motion_only = false;
+        if self.frame_pending {
             return;
         }
-        self.needs_redraw = false;
+        if let Some(ref overlay_surface) = self.overlay_surface {
+            overlay_surface
+                .wl_surface()
+                .frame(qh, overlay_surface.wl_surface().clone());
+            overlay_surface.wl_surface().commit();
+            self.frame_pending = true;
+        }
+    }
 
-        let phys_width = self.screenshot.width;
-        let phys_height = self.screenshot.height;
+    fn handle_action(&mut self, action: Action, conn: &Connection, qh: &QueueHandle<Self>) {
+        match action {
+            Action::Recapture => self.recapture(conn, qh),
+            Action::CycleContrast => {
+                self.contrast = match self.contrast {
+                    Contrast::Off => Contrast::Cursor,
+                    Contrast::Cursor => Contrast::PerLine,
+                    Contrast::PerLine => Contrast::Off,
+                };
+            }
+            Action::CycleUnit => {
+                self.unit = match self.unit {
+                    Unit::Logical => Unit::Device,
+                    Unit::Device => Unit::Rem,
+                    Unit::Rem => Unit::Logical,
+                };
+            }
+            Action::ToggleGridSnap => self.grid_snap = !self.grid_snap,
+            Action::ToggleLinearBlend => self.linear_blend = !self.linear_blend,
+            Action::ToggleAntialias => self.antialias = !self.antialias,
+            Action::ToggleRuler => self.ruler = !self.ruler,
+            Action::ToggleRoundSizes => self.round_sizes = !self.round_sizes,
+            Action::CycleLabelCorner => {
+                self.label_corner = match self.label_corner {
+                    None => Some(Corner::TopLeft),
+                    Some(Corner::TopLeft) => Some(Corner::TopRight),
+                    Some(Corner::TopRight) => Some(Corner::BottomRight),
+                    Some(Corner::BottomRight) => Some(Corner::BottomLeft),
+                    Some(Corner::BottomLeft) => None,
+                };
+            }
+            Action::ToggleGaps => self.show_gaps = !self.show_gaps,
+            Action::ToggleMargins => self.show_margins = !self.show_margins,
+            Action::ToggleBaselines => self.show_baselines = !self.show_baselines,
+            Action::ToggleBorderBox => self.show_border_box = !self.show_border_box,
+            Action::ToggleRunWidth => self.run_width = !self.run_width,
+            Action::ToggleScreenSize => self.show_screen_size = !self.show_screen_size,
+            Action::ToggleContentBounds => self.show_content_bounds = !self.show_content_bounds,
+            Action::ToggleRunningTotal => {
+                self.running_total = match self.running_total {
+                    Some(_) => None,
+                    None => Some(RunningTotal::default()),
+                };
+            }
+            Action::ResetRunningTotal => {
+                if let Some(total) = &mut self.running_total {
+                    *total = RunningTotal::default();
+                }
+            }
+            Action::SaveCrop => self.save_crop(),
+            Action::ToggleEdgeMap => self.show_edge_map = !self.show_edge_map,
+            Action::CycleCrosshair => {
+                self.crosshair = match self.crosshair {
+                    CrosshairStyle::Cross => CrosshairStyle::Dot,
+                    CrosshairStyle::Dot => CrosshairStyle::Lines,
+                    CrosshairStyle::Lines => CrosshairStyle::Cross,
+                };
+            }
+            Action::CycleMode => {
+                self.mode = match self.mode {
+                    Mode::Auto => Mode::Edges,
+                    Mode::Edges => Mode::Drag,
+                    Mode::Drag => Mode::Auto,
+                };
+                if self.mode == Mode::Edges && self.is_dragging {
+                    self.cancel_drag(qh);
+                }
+            }
+            Action::ToggleExtensionLines => self.extension_lines = !self.extension_lines,
+            Action::CycleColorReadout => {
+                self.color_readout = match self.color_readout {
+                    None => Some(ColorFormat::Hex),
+                    Some(ColorFormat::Hex) => Some(ColorFormat::Rgb),
+                    Some(ColorFormat::Rgb) => Some(ColorFormat::Hsl),
+                    Some(ColorFormat::Hsl) => None,
+                };
+            }
+            Action::CopyColor => self.copy_color(),
+            Action::EnterSize => self.size_entry = Some(String::new()),
+            Action::TogglePointerLock => self.pointer_locked = !self.pointer_locked,
+            Action::TogglePrecision => self.toggle_precision(qh),
+            Action::ToggleLoupe => self.loupe = !self.loupe,
+            Action::ToggleNativeOrientation => self.native_orientation = !self.native_orientation,
+            Action::NextElement => self.step_element(1),
+            Action::PreviousElement => self.step_element(-1),
+            Action::OuterContainer => self.container_depth += 1,
+            Action::InnerContainer => {
+                self.container_depth = self.container_depth.saturating_sub(1);
+            }
+            Action::TogglePathMode => {
+                self.path_mode = !self.path_mode;
+                self.path.clear();
+            }
+            Action::ReportEdges => self.report_edges(),
+            Action::CopyMeasurement => self.copy_measurement(),
+            Action::CopyImage => self.copy_image(),
+            Action::ToggleRadial => {
+                self.radial_center = match self.radial_center {
+                    Some(_) => None,
+                    None => Some((self.pointer_x, self.pointer_y)),
+                };
+            }
+            Action::TogglePin => {
+                self.pin = match self.pin {
+                    Some(_) => None,
+                    None => Some((self.pointer_x, self.pointer_y)),
+                };
+            }
+            Action::ToggleWindowOrigin => {
+                self.window_origin = match self.window_origin {
+                    Some(_) => None,
+                    None => get_active_window_geometry(),
+                };
+            }
+            Action::ToggleSmoothing => {
+                self.smoothing = !self.smoothing;
+                self.edge_smoother = EdgeSmoother::default();
+            }
+            Action::ToggleEdgeConfidence => self.edge_confidence = !self.edge_confidence,
+            Action::ToggleScanBounds => {
+                self.scan_bounds = match self.scan_bounds {
+                    Some(_) => None,
+                    None => self.drag_rect,
+                };
+            }
+            Action::ToggleHelp => self.show_help = !self.show_help,
+            Action::Quit => self.exit = true,
+            Action::GrowLabels => {
+                self.font_size = (self.font_size + FONT_SIZE_STEP).min(FONT_SIZE_RANGE.1);
+            }
+            Action::ShrinkLabels => {
+                self.font_size = self
+                    .font_size
+                    .saturating_sub(FONT_SIZE_STEP)
+                    .max(FONT_SIZE_RANGE.0);
+            }
+        }
+        self.request_redraw(qh);
+    }
 
-        // Derive scale from screenshot vs surface dimensions if fractional scale not set
-        if self.scale == 1.0 && self.width > 0 {
-            self.scale = phys_width as f64 / self.width as f64;
+    /// Current state of the setting behind `action`, for the help panel
+    fn action_state(&self, action: Action) -> Option<&'static str> {
+        let on_off = |on: bool| Some(if on { "on" } else { "off" });
+        match action {
+            Action::CycleContrast => Some(self.contrast.name()),
+            Action::CycleUnit => Some(self.unit.name()),
+            Action::ToggleGridSnap => on_off(self.grid_snap),
+            Action::ToggleLinearBlend => on_off(self.linear_blend),
+            Action::ToggleAntialias => on_off(self.antialias),
+            Action::ToggleRuler => on_off(self.ruler),
+            Action::ToggleRoundSizes => on_off(self.round_sizes),
+            Action::ToggleGaps => on_off(self.show_gaps),
+            Action::ToggleMargins => on_off(self.show_margins),
+            Action::ToggleBaselines => on_off(self.show_baselines),
+            Action::ToggleBorderBox => on_off(self.show_border_box),
+            Action::ToggleRunWidth => on_off(self.run_width),
+            Action::ToggleScreenSize => on_off(self.show_screen_size),
+            Action::ToggleContentBounds => on_off(self.show_content_bounds),
+            Action::ToggleRunningTotal => on_off(self.running_total.is_some()),
+            Action::ToggleEdgeMap => on_off(self.show_edge_map),
+            Action::ToggleScanBounds => on_off(self.scan_bounds.is_some()),
+            Action::ToggleSmoothing => on_off(self.smoothing),
+            Action::ToggleEdgeConfidence => on_off(self.edge_confidence),
+            Action::CycleCrosshair => Some(self.crosshair.name()),
+            Action::CycleMode => Some(self.mode.name()),
+            Action::ToggleWindowOrigin => on_off(self.window_origin.is_some()),
+            Action::TogglePathMode => on_off(self.path_mode),
+            Action::ToggleRadial => on_off(self.radial_center.is_some()),
+            Action::TogglePin => on_off(self.pin.is_some()),
+            Action::ToggleExtensionLines => on_off(self.extension_lines),
+            Action::CycleLabelCorner => Some(self.label_corner.map_or("off", Corner::name)),
+            Action::TogglePointerLock => on_off(self.pointer_locked),
+            Action::TogglePrecision => on_off(self.precision_lock.is_some()),
+            Action::ToggleLoupe => on_off(self.loupe),
+            Action::ToggleNativeOrientation => on_off(self.native_orientation),
+            Action::CycleColorReadout => Some(self.color_readout.map_or("off", ColorFormat::name)),
+            Action::Recapture
+            | Action::SaveCrop
+            | Action::CopyColor
+            | Action::ReportEdges
+            | Action::CopyMeasurement
+            | Action::CopyImage
+            | Action::ResetRunningTotal
+            | Action::EnterSize
+            | Action::NextElement
+            | Action::PreviousElement
+            | Action::OuterContainer
+            | Action::InnerContainer
+            | Action::ToggleHelp
+            | Action::Quit
+            | Action::GrowLabels
+            | Action::ShrinkLabels => None,
         }
+    }
 
-        let cursor_phys_x = to_physical(self.pointer_x, self.scale);
-        let cursor_phys_y = to_physical(self.pointer_y, self.scale);
+    fn help_rows(&self) -> Vec<(String, String)> {
+        // An action moved with --bind is listed once, under its new key
+        let mut rebound = Vec::new();
+        let keys = BINDINGS.iter().chain(CTRL_BINDINGS).filter_map(|b| {
+            let description = match self.action_state(b.action) {
+                Some(state) => format!("{} [{}]", b.description, state),
+                None => b.description.to_string(),
+            };
+            let key = match self
+                .config
+                .keymap
+                .iter()
+                .find(|bind| bind.action == b.action)
+            {
+                Some(_) if rebound.contains(&b.action) => return None,
+                Some(bind) => {
+                    rebound.push(b.action);
+                    key_name(bind.keysym)
+                }
+                None => b.key.to_string(),
+            };
+            Some((key, description))
+        });
+        let other = OTHER_BINDINGS
+            .iter()
+            .map(|(key, description)| (key.to_string(), description.to_string()));
+        keys.chain(other).collect()
+    }
 
-        let pool = self.pool.as_mut().unwrap();
-        let stride = phys_width as i32 * 4;
-        let size = (stride * phys_height as i32) as usize;
+    /// Physical corners of the rectangle being dragged, as (start, cursor) so the live label
+    /// can follow the cursor corner. With Alt held the rectangle grows from the click point
+    /// as its center instead of its corner.
+    fn drag_corners(&self) -> Option<(u32, u32, u32, u32)> {
+        if let (Some(handle), Some((mut x1, mut y1, mut x2, mut y2))) =
+            (self.resizing, self.drag_rect)
+        {
+            // Resizing moves only the grabbed sides
+            let x = to_physical(self.pointer_x, self.scale, self.screenshot.width);
+            let y = to_physical(self.pointer_y, self.scale, self.screenshot.height);
+            if handle.left {
+                x1 = x;
+            } else if handle.right {
+                x2 = x;
+            }
+            if handle.top {
+                y1 = y;
+            } else if handle.bottom {
+                y2 = y;
+            }
+            return Some((x1, y1, x2, y2));
+        }
+        let (start_x, start_y) = self.drag_start?;
+        let (start_x, start_y) = if self.modifiers.alt {
+            (
+                (2.0 * start_x - self.pointer_x).clamp(0.0, self.width as f64),
+                (2.0 * start_y - self.pointer_y).clamp(0.0, self.height as f64),
+            )
+        } else {
+            (start_x, start_y)
+        };
+        Some((
+            to_physical(start_x, self.scale, self.screenshot.width),
+            to_physical(start_y, self.scale, self.screenshot.height),
+            to_physical(self.pointer_x, self.scale, self.screenshot.width),
+            to_physical(self.pointer_y, self.scale, self.screenshot.height),
+        ))
+    }
 
-        if pool.len() < size {
-            pool.resize(size).expect("Failed to resize pool");
+    /// Move the measurement to the neighbouring element to the right (`direction` 1) or
+    /// left (-1) of the selected one, starting from the element under the cursor
+    fn step_element(&mut self, direction: i32) {
+        let (x, y) = self.selected_element.unwrap_or((
+            to_physical(self.pointer_x, self.scale, self.screenshot.width),
+            to_physical(self.pointer_y, self.scale, self.screenshot.height),
+        ));
+        let gaps = find_gaps(
+            &self.screenshot,
+            x,
+            y,
+            self.scan_bounds,
+            &self.scan_options(),
+        );
+        // The neighbour starts just past the gap; there is none if the gap runs off screen
+        let next_x = if direction > 0 {
+            gaps.right.map(|(_, end)| end + 1)
+        } else {
+            gaps.left.and_then(|(start, _)| start.checked_sub(1))
+        };
+        if let Some(next_x) = next_x.filter(|&x| x < self.screenshot.width) {
+            self.selected_element = Some((next_x, y));
+            self.edge_smoother = EdgeSmoother::default();
         }
+    }
 
-        let (buffer, canvas) = pool
-            .create_buffer(
-                phys_width as i32,
-                phys_height as i32,
-                stride,
-                wl_shm::Format::Argb8888,
+    /// Feed a key to the size entry: digits and `x` build the `WxH` text, Enter draws it
+    /// (or clears the rectangle when empty), Backspace deletes and Escape cancels
+    fn type_size(&mut self, keysym: Keysym, qh: &QueueHandle<Self>) {
+        let Some(entry) = self.size_entry.as_mut() else {
+            return;
+        };
+        match keysym {
+            Keysym::Return | Keysym::KP_Enter => {
+                self.typed_size = entry.split_once('x').and_then(|(width, height)| {
+                    Some((width.parse().ok()?, height.parse().ok()?))
+                        .filter(|&(width, height)| width > 0 && height > 0)
+                });
+                self.size_entry = None;
+            }
+            Keysym::Escape => self.size_entry = None,
+            Keysym::BackSpace => {
+                entry.pop();
+            }
+            keysym => match keysym.key_char() {
+                Some(c @ '0'..='9') => entry.push(c),
+                Some('x' | 'X' | '*') if !entry.is_empty() && !entry.contains('x') => {
+                    entry.push('x')
+                }
+                _ => {}
+            },
+        }
+        self.request_redraw(qh);
+    }
+
+    /// Enter or leave precision mode: lock the pointer in place and move the measurement
+    /// point by scaled-down relative motion. Does nothing without pointer constraints and
+    /// relative pointer support.
+    fn toggle_precision(&mut self, qh: &QueueHandle<Self>) {
+        if let Some(lock) = self.precision_lock.take() {
+            // Leave the visible cursor where the precise point ended up
+            lock.set_cursor_position_hint(self.pointer_x, self.pointer_y);
+            lock.destroy();
+            if let Some(overlay_surface) = &self.overlay_surface {
+                overlay_surface.wl_surface().commit();
+            }
+            return;
+        }
+        let (Some(overlay_surface), Some(pointer)) = (&self.overlay_surface, &self.pointer) else {
+            return;
+        };
+        if self.relative_pointer.is_none() {
+            return;
+        }
+        self.precision_lock = self
+            .pointer_constraints
+            .lock_pointer(
+                overlay_surface.wl_surface(),
+                pointer,
+                None,
+                Lifetime::Persistent,
+                qh,
             )
-            .expect("Failed to create buffer");
+            .ok();
+    }
 
-        // Copy pre-converted BGRA background
-        let bgra = self.screenshot.bgra_data();
-        let bgra_size = bgra.len().min(size);
-        canvas[..bgra_size].copy_from_slice(&bgra[..bgra_size]);
+    /// Move the measurement point to (x, y) in logical coordinates
+    fn move_pointer(&mut self, x: f64, y: f64, qh: &QueueHandle<Self>) {
+        // Nothing else to redraw for, and nothing the motion itself changes besides the cursor
+        let motion_only = (!self.needs_redraw || self.motion_only)
+            && self.selected_element.is_none()
+            && self.container_depth == 0
+            && !self.is_dragging;
+        self.pointer_x = x;
+        self.pointer_y = y;
+        // Pointer movement takes the measurement back from Tab selection
+        self.selected_element = None;
+        self.container_depth = 0;
+        if self.path_mode && self.is_dragging {
+            self.path.push((self.pointer_x, self.pointer_y));
+        }
+        // Request frame callback - don't draw directly
+        self.request_redraw(qh);
+        self.motion_only = motion_only;
+    }
 
-        // Draw overlay
+    /// Move the drawn rectangle one logical pixel (ten with Shift) in the direction of an
+    /// arrow key, stopping at the screen edges
+    fn nudge_rect(&mut self, keysym: Keysym, qh: &QueueHandle<Self>) {
+        let (Some((x1, y1, x2, y2)), Some((dx, dy))) = (self.drag_rect, arrow_direction(keysym))
+        else {
+            return;
+        };
+        let step = if self.modifiers.shift {
+            NUDGE_STEP_LARGE
+        } else {
+            1.0
+        };
+        let step = (step * self.scale).round().max(1.0) as i64;
+        let shift = |low: u32, high: u32, delta: i32, size: u32| {
+            let max = (size - 1).saturating_sub(high - low) as i64;
+            let low = (low as i64 + delta as i64 * step).clamp(0, max) as u32;
+            (low, low + (high - low))
+        };
+        let (x1, x2) = shift(x1, x2, dx, self.screenshot.width);
+        let (y1, y2) = shift(y1, y2, dy, self.screenshot.height);
+        self.drag_rect = Some((x1, y1, x2, y2));
+        self.request_redraw(qh);
+    }
+
+    /// Handle of the drawn rectangle under the pointer, if one can be grabbed to resize it
+    fn hovered_handle(&self) -> Option<ResizeHandle> {
+        if self.is_dragging || self.path_mode || self.mode == Mode::Edges {
+            return None;
+        }
+        resize_handle(
+            self.drag_rect?,
+            to_physical(self.pointer_x, self.scale, self.screenshot.width),
+            to_physical(self.pointer_y, self.scale, self.screenshot.height),
+            (RESIZE_HANDLE_REACH * self.scale).round() as u32,
+        )
+    }
+
+    /// Show a resize cursor over the drawn rectangle's handles and the crosshair elsewhere
+    fn update_cursor_shape(&mut self) {
+        let shape = match self.resizing.or_else(|| self.hovered_handle()) {
+            Some(handle) => handle.cursor_shape(),
+            None => wp_cursor_shape_device_v1::Shape::Crosshair,
+        };
+        if shape != self.cursor_shape
+            && let Some(device) = &self.cursor_shape_device
+        {
+            device.set_shape(self.enter_serial, shape);
+            self.cursor_shape = shape;
+        }
+    }
+
+    /// Abort an in-progress drag without creating a rectangle
+    fn cancel_drag(&mut self, qh: &QueueHandle<Self>) {
+        self.drag_start = None;
+        self.resizing = None;
+        self.path.clear();
+        self.is_dragging = false;
+        self.request_redraw(qh);
+    }
+
+    /// Show a fully transparent buffer in place of the overlay, which hides it from a capture
+    /// like unmapping would, but keeps the surface mapped along with its keyboard focus and
+    /// pointer. Returns false where that isn't possible: before the first frame, or when the
+    /// buffer format has no alpha.
+    fn blank_overlay(&mut self) -> bool {
+        let format = choose_buffer_format(self.shm.formats());
+        let (Some(overlay_surface), Some(pool), Some(pixmap)) = (
+            &self.overlay_surface,
+            self.pool.as_mut(),
+            self.cached_pixmap.as_ref(),
+        ) else {
+            return false;
+        };
+        if !matches!(format, wl_shm::Format::Argb8888 | wl_shm::Format::Abgr8888) {
+            return false;
+        }
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        if self
+            .blank_buffer
+            .as_ref()
+            .is_none_or(|b| (b.stride(), b.height()) != (width * 4, height))
+        {
+            let Ok((buffer, canvas)) = pool.create_buffer(width, height, width * 4, format) else {
+                return false;
+            };
+            canvas.fill(0);
+            self.blank_buffer = Some(buffer);
+        }
+        let Some(buffer) = &self.blank_buffer else {
+            return false;
+        };
+        let surface = overlay_surface.wl_surface();
+        // Still attached from the last capture if the compositor hasn't released it yet
+        if buffer.attach_to(surface).is_err() {
+            surface.attach(Some(buffer.wl_buffer()), 0, 0);
+        }
+        surface.damage_buffer(0, 0, width, height);
+        surface.commit();
+        true
+    }
+
+    /// Re-capture the screen and swap in the fresh frame, keeping the current
+    /// cursor position so edge measurements re-detect on the new content
+    pub fn recapture(&mut self, conn: &Connection, qh: &QueueHandle<Self>) {
+        // A loaded image is read again, picking up edits to the file; nothing on screen is
+        // captured, so the overlay stays up
+        let result = if let Some(path) = &self.config.image {
+            load_image(path, self.config.linear_luminance)
+        } else {
+            // Hide the overlay first so the new frame can't include it. Requests go out in
+            // order on the shared connection, so the compositor applies this before the copy.
+            let blanked = self.config.live_fps.is_some() && self.blank_overlay();
+            if !blanked && let Some(overlay_surface) = &self.overlay_surface {
+                let surface = overlay_surface.wl_surface();
+                surface.attach(None, 0, 0);
+                surface.commit();
+            }
+            let result = capture_screen(
+                conn,
+                self.target_output_name.as_deref(),
+                self.transform,
+                self.config.region,
+                self.config.linear_luminance,
+            );
+            if blanked {
+                // The next frame replaces the blank buffer whole, even if the capture failed
+                self.background_changed = true;
+                self.request_redraw(qh);
+            } else if let Some(overlay_surface) = &self.overlay_surface {
+                // An empty commit asks for a new configure, which remaps the overlay with a
+                // fresh draw
+                overlay_surface.commit();
+            }
+            result
+        };
+        let screenshot = match result {
+            Ok(screenshot) => screenshot,
+            Err(e) => {
+                eprintln!("hypruler: {}", e);
+                return;
+            }
+        };
+        self.diff = self
+            .reference
+            .as_ref()
+            .map(|reference| diff(&screenshot, reference));
+        self.screenshot = screenshot;
+        self.background_changed = true;
+        self.edge_map = None;
+        self.content_bounds = None;
+        if self.config.mask_cursor {
+            self.unmasked_cursor = Some((self.pointer_x, self.pointer_y));
+        }
+        self.request_redraw(qh);
+    }
+
+    /// Copy the color under the cursor to the clipboard, in the readout's leading format
+    fn copy_color(&self) {
+        let Some(rgb) = self.screenshot.rgb(
+            to_physical(self.pointer_x, self.scale, self.screenshot.width),
+            to_physical(self.pointer_y, self.scale, self.screenshot.height),
+        ) else {
+            return;
+        };
+        let text = self.color_readout.unwrap_or_default().format(rgb);
+        if let Err(e) = clipboard::copy_text(&text, self.config.copy_target) {
+            eprintln!("hypruler: failed to copy color: {}", e);
+        }
+    }
+
+    /// Copy the current selection as a `WxH+X+Y` geometry, as printed by `--geometry`
+    fn copy_measurement(&self) {
+        if let Err(e) = clipboard::copy_text(&self.geometry(), self.config.copy_target) {
+            eprintln!("hypruler: failed to copy measurement: {}", e);
+        }
+    }
+
+    /// Copy the drawn rectangle, or the whole capture, with the overlay as last drawn on
+    /// top, as a PNG image
+    fn copy_image(&self) {
+        let (x1, y1, x2, y2) =
+            self.drag_rect
+                .unwrap_or((0, 0, self.screenshot.width - 1, self.screenshot.height - 1));
+        let png = match &self.cached_pixmap {
+            Some(overlay) => self.screenshot.annotated_png(x1, y1, x2, y2, overlay),
+            None => self.screenshot.crop_png(x1, y1, x2, y2),
+        };
+        if let Err(e) = png.and_then(|png| clipboard::copy_png(&png, self.config.copy_target)) {
+            eprintln!("hypruler: failed to copy image: {}", e);
+        }
+    }
+
+    /// Print every luminance transition through the cursor as JSON on stderr
+    fn report_edges(&self) {
+        let (x, y) = (
+            to_physical(self.pointer_x, self.scale, self.screenshot.width),
+            to_physical(self.pointer_y, self.scale, self.screenshot.height),
+        );
+        if x >= self.screenshot.width || y >= self.screenshot.height {
+            return;
+        }
+        let report = edge_report(&self.screenshot, x, y);
+        match serde_json::to_string(&report) {
+            Ok(json) => eprintln!("{}", json),
+            Err(e) => eprintln!("hypruler: failed to report edges: {}", e),
+        }
+    }
+
+    /// Save the drawn rectangle as a PNG in the configured directory
+    fn save_crop(&self) {
+        let Some((x1, y1, x2, y2)) = self.drag_rect else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = self
+            .config
+            .save_dir()
+            .join(format!("hypruler-{}.png", timestamp));
+
+        let result = self
+            .screenshot
+            .crop_png(x1, y1, x2, y2)
+            .and_then(|png| std::fs::write(&path, png).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => eprintln!("hypruler: saved {}", path.display()),
+            Err(e) => eprintln!("hypruler: failed to save {}: {}", path.display(), e),
+        }
+    }
+
+    fn draw(&mut self, qh: &QueueHandle<Self>) {
+        if self.overlay_surface.is_none() || self.pool.is_none() {
+            return;
+        }
+        if self.width == 0 || self.height == 0 || !self.needs_redraw {
+            return;
+        }
+        self.needs_redraw = false;
+        let motion_only = std::mem::take(&mut self.motion_only);
+
+        // Derive scale from screenshot vs surface dimensions if fractional scale not set. An
+        // image is shown 1:1 whatever its size, so keeps the scale the compositor reports.
+        if self.scale == 1.0 && self.width > 0 && self.config.image.is_none() {
+            self.scale = self.screenshot.width as f64 / self.width as f64;
+        }
+
+        // Size the buffer to the configured surface rather than the capture, which may differ
+        // (e.g. a partial layer surface); the capture is drawn 1:1 from the top-left and any
+        // part of the surface it doesn't cover stays transparent
+        let buffer_scale = if self.viewport.is_some() {
+            self.scale
+        } else {
+            self.scale.round()
+        };
+        let phys_width = (self.width as f64 * buffer_scale).round() as u32;
+        let phys_height = (self.height as f64 * buffer_scale).round() as u32;
+
+        if let Some((x, y)) = self.unmasked_cursor.take() {
+            self.mask_cursor(x, y);
+        }
+
+        let cursor_phys_x = to_physical(self.pointer_x, self.scale, self.screenshot.width);
+        let cursor_phys_y = to_physical(self.pointer_y, self.scale, self.screenshot.height);
+        let scan_options = self.scan_options();
+        let pixels_per_mm = self.pixels_per_mm();
+
+        // Cursor-following overlays are only drawn while the pointer is over the overlay
+        let cursor_visible = self.pointer_inside
+            && self.pointer_x * self.scale < self.screenshot.width as f64
+            && self.pointer_y * self.scale < self.screenshot.height as f64;
+        let cursor = cursor_visible.then_some((cursor_phys_x, cursor_phys_y));
+
+        // Motion within the pixel measured last frame finds the same edges and draws the
+        // same overlay, so skip recompositing it
+        let same_size = self
+            .cached_pixmap
+            .as_ref()
+            .is_some_and(|p| p.width() == phys_width && p.height() == phys_height);
+        if motion_only
+            && same_size
+            && !self.background_changed
+            && cursor.is_some()
+            && cursor == self.drawn_cursor
+        {
+            return;
+        }
+        self.drawn_cursor = cursor;
+
+        let help_rows = self.show_help.then(|| self.help_rows());
+        let drag_corners = self.drag_corners();
+        if self.show_edge_map && self.edge_map.is_none() {
+            self.edge_map = Some(edge_mask(&self.screenshot));
+        }
+        if self.show_content_bounds && self.content_bounds.is_none() {
+            // The corner pixel stands in for the background, e.g. a letterbox bar
+            let background = self.screenshot.get_luminance(0, 0);
+            self.content_bounds = Some(find_content_bounds(&self.screenshot, background));
+        }
+        let format = choose_buffer_format(self.shm.formats());
         let needs_new_pixmap = self
             .cached_pixmap
             .as_ref()
@@ -260,72 +1487,463 @@ impl WaylandApp {
             self.cached_pixmap = Pixmap::new(phys_width, phys_height);
         }
 
+        let pool = self.pool.as_mut().unwrap();
+        let row_bytes = phys_width as usize * 4;
+        let size = row_bytes * phys_height as usize;
+
+        // A new size invalidates every buffer; a new background only their contents
+        let background_changed = needs_new_pixmap || std::mem::take(&mut self.background_changed);
+        if needs_new_pixmap {
+            self.buffers.clear();
+        } else if background_changed {
+            for (_, stale) in &mut self.buffers {
+                *stale = Some((0, 0, phys_width - 1, phys_height - 1));
+            }
+        }
+
+        // Draw into whichever buffer the compositor has released, so only the area its
+        // last overlay covered needs restoring; add a fresh one if all are still in use
+        let index = match self
+            .buffers
+            .iter()
+            .position(|(b, _)| b.canvas(pool).is_some())
+        {
+            Some(index) => index,
+            None => {
+                if self.buffers.len() >= BUFFER_COUNT {
+                    self.buffers.remove(0);
+                }
+                if pool.len() < size {
+                    pool.resize(size).expect("Failed to resize pool");
+                }
+                let (buffer, _) = pool
+                    .create_buffer(
+                        phys_width as i32,
+                        phys_height as i32,
+                        row_bytes as i32,
+                        format,
+                    )
+                    .expect("Failed to create buffer");
+                let full = (0, 0, phys_width - 1, phys_height - 1);
+                self.buffers.push((buffer, Some(full)));
+                self.buffers.len() - 1
+            }
+        };
+        let (buffer, stale) = &mut self.buffers[index];
+        let canvas = buffer.canvas(pool).expect("Buffer still in use");
+
+        // The buffer may be padded, so copy the tightly packed BGRA background row by row
+        let stride = buffer.stride() as usize;
+        let restore = stale.take();
+        if let Some((x1, y1, x2, y2)) = restore {
+            let span = x1 as usize * 4..(x2 as usize + 1) * 4;
+            for y in y1 as usize..=y2 as usize {
+                self.screenshot
+                    .copy_row(y as u32, x1, x2, &mut canvas[y * stride..][span.clone()]);
+            }
+        }
+
+        // Draw overlay
+        let palette = if self.contrast == Contrast::Off {
+            Palette::default()
+        } else {
+            Palette::high_contrast(self.screenshot.average_luminance(
+                cursor_phys_x,
+                cursor_phys_y,
+                CONTRAST_SAMPLE_RADIUS,
+            ))
+        };
+        let opts = DrawOptions {
+            font: self.font.as_ref(),
+            font_size: self.font_size,
+            scale: self.scale,
+            palette,
+            unit: self.unit,
+            linear_blend: self.linear_blend,
+            antialias: self.antialias,
+            ruler: self.ruler,
+            round_sizes: self.round_sizes,
+            label_corner: self.label_corner,
+            rem_base: self.config.rem_base,
+            extension_lines: self.extension_lines,
+            transposed: self.native_orientation && self.transform % 2 == 1,
+            target: self.config.target,
+            contrast_background: (self.contrast == Contrast::PerLine).then_some(&self.screenshot),
+            status_bar: self.config.status_bar,
+            pixels_per_mm,
+            real_unit: self.config.real_unit,
+        };
+
+        // Set when smoothed edges still lag the detected ones and need another frame
+        let mut unsettled = false;
+        // Element measured at the cursor this frame, with its corner radius
+        let mut measurement = None;
+
         let pixmap = self.cached_pixmap.as_mut().unwrap();
         pixmap.fill(tiny_skia::Color::TRANSPARENT);
 
+        if let Some(mask) = self.edge_map.as_deref().filter(|_| self.show_edge_map) {
+            draw_edge_map(pixmap, mask, self.screenshot.width, &opts);
+        }
+
+        if let Some(diff) = &self.diff {
+            draw_diff(pixmap, diff, self.screenshot.width, &opts);
+        }
+
+        if let Some(bounds) = self.scan_bounds {
+            draw_scan_bounds(pixmap, bounds, &opts);
+        }
+
+        if let Some((x1, y1, x2, y2)) = self
+            .content_bounds
+            .flatten()
+            .filter(|_| self.show_content_bounds)
+        {
+            draw_rectangle_measurement(pixmap, x1, y1, x2, y2, false, &opts);
+        }
+
+        for &(x1, y1, x2, y2) in &self.pinned_rects {
+            draw_rectangle_measurement(pixmap, x1, y1, x2, y2, false, &opts);
+        }
+
+        if !self.path.is_empty() {
+            let points: Vec<_> = self
+                .path
+                .iter()
+                .map(|&(x, y)| ((x * self.scale) as f32, (y * self.scale) as f32))
+                .collect();
+            draw_path(pixmap, &points, &opts);
+        }
+
+        if let Some((x, y)) = self.radial_center.filter(|_| cursor_visible) {
+            draw_radial_measurement(
+                pixmap,
+                (
+                    to_physical(x, self.scale, self.screenshot.width),
+                    to_physical(y, self.scale, self.screenshot.height),
+                ),
+                (cursor_phys_x, cursor_phys_y),
+                &opts,
+            );
+        }
+
+        if let Some((x, y)) = self.pin.filter(|_| cursor_visible) {
+            draw_pin_delta(
+                pixmap,
+                (
+                    to_physical(x, self.scale, self.screenshot.width),
+                    to_physical(y, self.scale, self.screenshot.height),
+                ),
+                (cursor_phys_x, cursor_phys_y),
+                &opts,
+            );
+        }
+
+        if let Some((width, height)) = self.typed_size {
+            let phys = |logical: u32| (logical as f64 * self.scale).round() as u32;
+            // Any size can be typed, so keep the rectangle within the capture
+            let x1 = cursor_phys_x.min(self.screenshot.width - 1);
+            let y1 = cursor_phys_y.min(self.screenshot.height - 1);
+            draw_rectangle_measurement(
+                pixmap,
+                x1,
+                y1,
+                x1.saturating_add(phys(width).saturating_sub(1))
+                    .min(self.screenshot.width - 1),
+                y1.saturating_add(phys(height).saturating_sub(1))
+                    .min(self.screenshot.height - 1),
+                false,
+                &opts,
+            );
+        }
+        if let Some(entry) = &self.size_entry {
+            let text = format!("Size: {}_ (WxH, Enter)", entry);
+            draw_prompt(
+                pixmap,
+                &text,
+                cursor_phys_x as f32,
+                cursor_phys_y as f32,
+                &opts,
+            );
+        }
+
         if self.is_dragging {
             // Draw rectangle from drag start to current cursor
-            if let Some((start_x, start_y)) = self.drag_start {
-                let (left, top, right, bottom) = normalize_rect(
-                    to_physical(start_x, self.scale),
-                    to_physical(start_y, self.scale),
-                    cursor_phys_x,
-                    cursor_phys_y,
-                );
-                draw_rectangle_measurement(
-                    pixmap,
-                    left,
-                    top,
-                    right,
-                    bottom,
-                    self.font.as_ref(),
-                    self.scale,
-                );
+            if let Some((x1, y1, x2, y2)) = drag_corners {
+                draw_rectangle_measurement(pixmap, x1, y1, x2, y2, true, &opts);
             }
-        } else if cursor_phys_x < self.screenshot.width && cursor_phys_y < self.screenshot.height {
+        } else {
             // Draw completed rectangle if exists
             if let Some((x1, y1, x2, y2)) = self.drag_rect {
-                draw_rectangle_measurement(pixmap, x1, y1, x2, y2, self.font.as_ref(), self.scale);
+                draw_rectangle_measurement(pixmap, x1, y1, x2, y2, false, &opts);
             }
+        }
 
-            // Always show edge detection and crosshair when not dragging
-            let edges = find_edges(&self.screenshot, cursor_phys_x, cursor_phys_y);
-            draw_measurements(
+        if !self.is_dragging && cursor_visible {
+            // Flag edges that nearly line up across persistent rectangles
+            let rects: Vec<_> = self
+                .pinned_rects
+                .iter()
+                .copied()
+                .chain(self.drag_rect)
+                .collect();
+            draw_misalignments(pixmap, &rects, &opts);
+
+            // Show edge detection when not dragging (unless only dragging is wanted),
+            // measuring the element picked with Tab if there is one
+            if self.mode != Mode::Drag {
+                let (probe_x, probe_y) = self
+                    .selected_element
+                    .unwrap_or((cursor_phys_x, cursor_phys_y));
+                let mut edges = if self.run_width {
+                    find_run_edges(&self.screenshot, probe_x, probe_y)
+                } else {
+                    find_edges(
+                        &self.screenshot,
+                        probe_x,
+                        probe_y,
+                        self.scan_bounds,
+                        &scan_options,
+                    )
+                };
+                // Walk out to the selected container, stopping at the outermost one
+                for level in 0..self.container_depth {
+                    match find_container(&self.screenshot, &edges, self.scan_bounds, &scan_options)
+                    {
+                        Some(container) => edges = container,
+                        None => {
+                            self.container_depth = level;
+                            break;
+                        }
+                    }
+                }
+                if self.smoothing {
+                    let (smoothed, settled) = self.edge_smoother.update(edges);
+                    edges = smoothed;
+                    unsettled = !settled;
+                }
+                if self.selected_element.is_some() {
+                    draw_element_outline(pixmap, &edges, &opts);
+                }
+                let radius = estimate_corner_radius(&self.screenshot, &edges, probe_x, probe_y);
+                let strength = self
+                    .edge_confidence
+                    .then(|| edge_strength(&self.screenshot, &edges, probe_x, probe_y));
+                draw_measurements(
+                    pixmap,
+                    &edges,
+                    radius,
+                    strength.as_ref(),
+                    probe_x,
+                    probe_y,
+                    &opts,
+                );
+                measurement = Some((edges, radius));
+                if self.show_gaps {
+                    let gaps = find_gaps(
+                        &self.screenshot,
+                        probe_x,
+                        probe_y,
+                        self.scan_bounds,
+                        &scan_options,
+                    );
+                    draw_gaps(pixmap, &gaps, probe_x, probe_y, &opts);
+                }
+                if self.show_margins {
+                    draw_margins(pixmap, &edges, probe_x, probe_y, &opts);
+                }
+                if self.show_border_box
+                    && let Some(border) = find_border_box(
+                        &self.screenshot,
+                        &edges,
+                        probe_x,
+                        probe_y,
+                        self.scan_bounds,
+                        &scan_options,
+                    )
+                {
+                    draw_border_box(pixmap, &border, &opts);
+                }
+                if self.show_baselines {
+                    let text =
+                        find_text_lines(&self.screenshot, probe_x, probe_y, self.scan_bounds);
+                    draw_baselines(pixmap, &text, probe_x, probe_y, &opts);
+                }
+            }
+            if let Some((x, y, width, height)) = self.window_origin {
+                let phys = |logical: i32| (logical as f64 * self.scale).round() as i32;
+                draw_window_origin(
+                    pixmap,
+                    (phys(x), phys(y), phys(width), phys(height)),
+                    cursor_phys_x,
+                    cursor_phys_y,
+                    &opts,
+                );
+            }
+            draw_crosshair(
+                pixmap,
+                cursor_phys_x as f32,
+                cursor_phys_y as f32,
+                self.crosshair,
+                &opts.palette,
+                opts.antialias,
+            );
+        }
+
+        if let Some(position) = self.config.status_bar {
+            let color = self
+                .screenshot
+                .rgb(cursor_phys_x, cursor_phys_y)
+                .map(|rgb| (self.color_readout.unwrap_or_default(), rgb));
+            // A position, unlike a size, moves as well as swaps under native orientation
+            let cursor = if self.native_orientation {
+                let (width, height) = (self.screenshot.width, self.screenshot.height);
+                let cursor = (cursor_phys_x, cursor_phys_y, cursor_phys_x, cursor_phys_y);
+                let (x, y, _, _) = native_rect(cursor, width, height, self.transform);
+                (x, y)
+            } else {
+                (cursor_phys_x, cursor_phys_y)
+            };
+            draw_status_bar(
+                pixmap,
+                position,
+                cursor,
+                measurement.as_ref().map(|(edges, radius)| (edges, *radius)),
+                color,
+                &opts,
+            );
+        }
+
+        // What --watch-file reports: the rectangle being dragged or drawn, else the element
+        let watched = if self.is_dragging {
+            drag_corners
+        } else {
+            self.drag_rect.or(measurement
+                .as_ref()
+                .map(|(edges, _)| (edges.left, edges.up, edges.right, edges.down)))
+        };
+
+        if let Some(format) = self.color_readout.filter(|_| cursor_visible)
+            && let Some(rgb) = self.screenshot.rgb(cursor_phys_x, cursor_phys_y)
+        {
+            draw_color_readout(
                 pixmap,
-                &edges,
+                rgb,
+                format,
+                cursor_phys_x as f32,
+                cursor_phys_y as f32,
+                &opts,
+            );
+        }
+
+        if self.loupe && cursor_visible {
+            draw_loupe(
+                pixmap,
+                &self.screenshot,
                 cursor_phys_x,
                 cursor_phys_y,
-                self.font.as_ref(),
-                self.scale,
+                self.loupe_zoom,
+                &opts,
             );
-            draw_crosshair(pixmap, cursor_phys_x as f32, cursor_phys_y as f32);
         }
 
-        // Composite overlay onto canvas
-        let overlay_data = pixmap.data();
-        for (i, chunk) in canvas[..size].chunks_exact_mut(4).enumerate() {
-            let src_idx = i * 4;
-            let alpha = overlay_data[src_idx + 3];
-            if alpha > 0 {
-                let src_r = overlay_data[src_idx] as u32;
-                let src_g = overlay_data[src_idx + 1] as u32;
-                let src_b = overlay_data[src_idx + 2] as u32;
-                let src_a = alpha as u32;
+        if self.show_screen_size {
+            draw_screen_size(pixmap, &opts);
+        }
+
+        if let Some(total) = &self.running_total {
+            draw_running_total(pixmap, total, &opts);
+        }
 
-                let dst_b = chunk[0] as u32;
-                let dst_g = chunk[1] as u32;
-                let dst_r = chunk[2] as u32;
+        if let Some(rows) = help_rows {
+            draw_help(pixmap, &rows, &opts);
+        }
 
-                let inv_a = 255 - src_a;
-                chunk[0] = ((src_b * src_a + dst_b * inv_a) / 255) as u8;
-                chunk[1] = ((src_g * src_a + dst_g * inv_a) / 255) as u8;
-                chunk[2] = ((src_r * src_a + dst_r * inv_a) / 255) as u8;
-                chunk[3] = 255;
+        // Composite overlay onto canvas, tracking the bounds of what was drawn
+        let overlay_data = pixmap.data();
+        let mut overlay_bounds: Option<Bounds> = None;
+        for (y, (dst_row, src_row)) in canvas
+            .chunks_exact_mut(stride)
+            .zip(overlay_data.chunks_exact(row_bytes))
+            .enumerate()
+        {
+            let mut row_span: Option<(u32, u32)> = None;
+            for (x, (chunk, src)) in dst_row[..row_bytes]
+                .chunks_exact_mut(4)
+                .zip(src_row.chunks_exact(4))
+                .enumerate()
+            {
+                let alpha = src[3];
+                if alpha == 0 {
+                    continue;
+                }
+                let x = x as u32;
+                row_span = Some(row_span.map_or((x, x), |(first, _)| (first, x)));
+
+                if self.linear_blend {
+                    // Overlay is premultiplied: un-premultiply, then blend in linear light
+                    let src_a = alpha as u32;
+                    let a = alpha as f32 / 255.0;
+                    let blend = |src: u8, dst: u8| {
+                        let src = srgb_to_linear((src as u32 * 255 / src_a) as u8);
+                        linear_to_srgb(src * a + srgb_to_linear(dst) * (1.0 - a))
+                    };
+                    chunk[0] = blend(src[2], chunk[0]);
+                    chunk[1] = blend(src[1], chunk[1]);
+                    chunk[2] = blend(src[0], chunk[2]);
+                    chunk[3] = 255;
+                } else {
+                    let src_r = src[0] as u32;
+                    let src_g = src[1] as u32;
+                    let src_b = src[2] as u32;
+                    let src_a = alpha as u32;
+
+                    let dst_b = chunk[0] as u32;
+                    let dst_g = chunk[1] as u32;
+                    let dst_r = chunk[2] as u32;
+
+                    let inv_a = 255 - src_a;
+                    chunk[0] = ((src_b * src_a + dst_b * inv_a) / 255) as u8;
+                    chunk[1] = ((src_g * src_a + dst_g * inv_a) / 255) as u8;
+                    chunk[2] = ((src_r * src_a + dst_r * inv_a) / 255) as u8;
+                    chunk[3] = 255;
+                }
+            }
+            if let Some((first, last)) = row_span {
+                let y = y as u32;
+                overlay_bounds = Some(union_rect(overlay_bounds, (first, y, last, y)));
             }
         }
 
-        let layer_surface = self.layer_surface.as_ref().unwrap();
-        let surface = layer_surface.wl_surface();
+        // The canvas is composited in BGRA byte order; the BGR formats want RGBA
+        let touched = match (restore, overlay_bounds) {
+            (Some(restored), cur) => Some(union_rect(cur, restored)),
+            (None, cur) => cur,
+        };
+        if let Some((x1, y1, x2, y2)) = touched
+            && matches!(format, wl_shm::Format::Abgr8888 | wl_shm::Format::Xbgr8888)
+        {
+            let span = x1 as usize * 4..(x2 as usize + 1) * 4;
+            for y in y1 as usize..=y2 as usize {
+                for chunk in canvas[y * stride..][span.clone()].chunks_exact_mut(4) {
+                    chunk.swap(0, 2);
+                }
+            }
+        }
+        *stale = overlay_bounds;
+
+        // Relative to the last committed frame, only the old and new overlay areas changed
+        let damage = if background_changed {
+            Some((0, 0, phys_width - 1, phys_height - 1))
+        } else {
+            match (self.overlay_bounds, overlay_bounds) {
+                (Some(prev), cur) => Some(union_rect(cur, prev)),
+                (None, cur) => cur,
+            }
+        };
+
+        let overlay_surface = self.overlay_surface.as_ref().unwrap();
+        let surface = overlay_surface.wl_surface();
 
         // Use viewport for fractional scaling, fall back to buffer_scale for integer
         if let Some(ref viewport) = self.viewport {
@@ -335,8 +1953,41 @@ impl WaylandApp {
         }
 
         buffer.attach_to(surface).expect("Failed to attach buffer");
-        surface.damage_buffer(0, 0, phys_width as i32, phys_height as i32);
+
+        if let Some((x1, y1, x2, y2)) = damage {
+            surface.damage_buffer(
+                x1 as i32,
+                y1 as i32,
+                (x2 - x1 + 1) as i32,
+                (y2 - y1 + 1) as i32,
+            );
+        }
+        self.overlay_bounds = overlay_bounds;
+        if unsettled {
+            surface.frame(qh, surface.clone());
+            self.needs_redraw = true;
+            self.frame_pending = true;
+        }
         surface.commit();
+
+        if let Some(rect) = watched {
+            self.update_watch_file(rect);
+        }
+    }
+
+    /// Write the measurement to `--watch-file` when it differs from the last one written
+    fn update_watch_file(&mut self, rect: Bounds) {
+        let Some(path) = &self.config.watch_file else {
+            return;
+        };
+        let geometry = self.format_geometry(rect);
+        if self.watched_geometry.as_ref() == Some(&geometry) {
+            return;
+        }
+        if let Err(e) = write_watch_file(path, &geometry) {
+            eprintln!("hypruler: failed to write {}: {}", path.display(), e);
+        }
+        self.watched_geometry = Some(geometry);
     }
 }
 
@@ -368,6 +2019,7 @@ impl CompositorHandler for WaylandApp {
     }
 
     fn frame(&mut self, _: &Connection, qh: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {
+        self.frame_pending = false;
         self.draw(qh);
     }
 
@@ -399,6 +2051,27 @@ impl OutputHandler for WaylandApp {
     fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
 }
 
+impl WaylandApp {
+    /// Size the overlay to what the compositor configured, in logical pixels, and draw it
+    fn configure_overlay(&mut self, width: u32, height: u32, qh: &QueueHandle<Self>) {
+        self.width = width;
+        self.height = height;
+
+        let phys_width = (self.width as f64 * self.scale).round() as u32;
+        let phys_height = (self.height as f64 * self.scale).round() as u32;
+        let pool_size = (phys_width * phys_height * 4) as usize;
+
+        if self.pool.is_none() {
+            self.pool = Some(SlotPool::new(pool_size, &self.shm).expect("Failed to create pool"));
+        }
+
+        // A callback requested before the surface was mapped may never come
+        self.frame_pending = false;
+        self.needs_redraw = true;
+        self.draw(qh);
+    }
+}
+
 impl LayerShellHandler for WaylandApp {
     fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
         self.exit = true;
@@ -412,19 +2085,31 @@ impl LayerShellHandler for WaylandApp {
         configure: LayerSurfaceConfigure,
         _: u32,
     ) {
-        self.width = configure.new_size.0;
-        self.height = configure.new_size.1;
-
-        let phys_width = self.width * self.scale as u32;
-        let phys_height = self.height * self.scale as u32;
-        let pool_size = (phys_width * phys_height * 4) as usize;
+        self.configure_overlay(configure.new_size.0, configure.new_size.1, qh);
+    }
+}
 
-        if self.pool.is_none() {
-            self.pool = Some(SlotPool::new(pool_size, &self.shm).expect("Failed to create pool"));
-        }
+impl WindowHandler for WaylandApp {
+    fn request_close(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &Window) {
+        self.exit = true;
+    }
 
-        self.needs_redraw = true;
-        self.draw(qh);
+    fn configure(
+        &mut self,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+        _: &Window,
+        configure: WindowConfigure,
+        _: u32,
+    ) {
+        // Without a suggested size, cover the captured output
+        let (output_width, output_height) = self.output_logical_size();
+        let (width, height) = configure.new_size;
+        self.configure_overlay(
+            width.map_or(output_width, |w| w.get()),
+            height.map_or(output_height, |h| h.get()),
+            qh,
+        );
     }
 }
 
@@ -444,9 +2129,15 @@ impl SeatHandler for WaylandApp {
     ) {
         if capability == Capability::Pointer
             && let Ok(pointer) = self.seat_state.get_pointer(qh, &seat)
-            && let Some(ref manager) = self.cursor_shape_manager
         {
-            self.cursor_shape_device = Some(manager.get_shape_device(&pointer, qh));
+            if let Some(ref manager) = self.cursor_shape_manager {
+                self.cursor_shape_device = Some(manager.get_shape_device(&pointer, qh));
+            }
+            self.relative_pointer = self
+                .relative_pointer_state
+                .get_relative_pointer(&pointer, qh)
+                .ok();
+            self.pointer = Some(pointer);
         }
 
         if capability == Capability::Keyboard {
@@ -489,13 +2180,32 @@ impl KeyboardHandler for WaylandApp {
 
     fn press_key(
         &mut self,
-        _: &Connection,
-        _: &QueueHandle<Self>,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
         _: &wl_keyboard::WlKeyboard,
         _: u32,
-        _: KeyEvent,
+        event: KeyEvent,
     ) {
-        self.exit = true;
+        self.last_activity = Instant::now();
+        match event.keysym {
+            // Modifiers are held for drag variants, never treat them as exit
+            keysym if keysym.is_modifier_key() => {}
+            keysym if self.size_entry.is_some() => self.type_size(keysym, qh),
+            // A finished rectangle stays selected, so arrows move it
+            keysym
+                if !self.is_dragging
+                    && self.drag_rect.is_some()
+                    && arrow_direction(keysym).is_some() =>
+            {
+                self.nudge_rect(keysym, qh)
+            }
+            keysym => match action_for(keysym, self.modifiers.ctrl, &self.config.keymap) {
+                Some(Action::Quit) if self.is_dragging => self.cancel_drag(qh),
+                Some(action) => self.handle_action(action, conn, qh),
+                // Unbound keys do nothing, so a stray key press doesn't dismiss the overlay
+                None => {}
+            },
+        }
     }
 
     fn release_key(
@@ -510,13 +2220,25 @@ impl KeyboardHandler for WaylandApp {
     fn update_modifiers(
         &mut self,
         _: &Connection,
-        _: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
         _: &wl_keyboard::WlKeyboard,
         _: u32,
-        _: Modifiers,
+        modifiers: Modifiers,
         _: RawModifiers,
         _: u32,
     ) {
+        self.modifiers = modifiers;
+        // With --click-through, holding Ctrl takes pointer input to measure
+        if self.config.click_through && modifiers.ctrl != self.input_captured {
+            self.set_input_captured(modifiers.ctrl);
+            if let Some(overlay_surface) = &self.overlay_surface {
+                overlay_surface.commit();
+            }
+        }
+        // Alt switches a drag in progress between corner and center anchoring
+        if self.is_dragging {
+            self.request_redraw(qh);
+        }
     }
 
     fn repeat_key(
@@ -538,48 +2260,133 @@ impl PointerHandler for WaylandApp {
         _: &wl_pointer::WlPointer,
         events: &[PointerEvent],
     ) {
+        self.last_activity = Instant::now();
+        let measure = self.config.measure_button.code();
+        let dismiss = self.config.dismiss_button.code();
+        let exit = self.config.exit_button.map(|button| button.code());
         for event in events {
             match event.kind {
                 PointerEventKind::Enter { serial } => {
                     if let Some(ref device) = self.cursor_shape_device {
                         device.set_shape(serial, wp_cursor_shape_device_v1::Shape::Crosshair);
                     }
+                    self.enter_serial = serial;
+                    self.cursor_shape = wp_cursor_shape_device_v1::Shape::Crosshair;
+                    if self.mask_on_enter {
+                        self.mask_on_enter = false;
+                        self.unmasked_cursor = Some(event.position);
+                    }
+                    self.pointer_inside = true;
+                    if self.pointer_locked {
+                        self.request_redraw(qh);
+                    } else {
+                        self.move_pointer(event.position.0, event.position.1, qh);
+                    }
                 }
+                PointerEventKind::Leave { .. } => {
+                    // Drop the cursor's measurement rather than leave it behind at the
+                    // last position, e.g. while the pointer is on another output
+                    self.pointer_inside = false;
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Motion { .. } if self.pointer_locked => {}
                 PointerEventKind::Motion { .. } => {
-                    self.pointer_x = event.position.0;
-                    self.pointer_y = event.position.1;
-                    self.needs_redraw = true;
-                    // Request frame callback - don't draw directly
-                    if let Some(ref layer_surface) = self.layer_surface {
-                        layer_surface
-                            .wl_surface()
-                            .frame(qh, layer_surface.wl_surface().clone());
-                        layer_surface.wl_surface().commit();
+                    self.move_pointer(event.position.0, event.position.1, qh);
+                    self.update_cursor_shape();
+                }
+                PointerEventKind::Axis { vertical, .. }
+                    if self.loupe && vertical.absolute != 0.0 =>
+                {
+                    // Scrolling up zooms the loupe in, down zooms out
+                    let (min, max) = LOUPE_ZOOM_RANGE;
+                    self.loupe_zoom = if vertical.absolute < 0.0 {
+                        (self.loupe_zoom * 2).min(max)
+                    } else {
+                        (self.loupe_zoom / 2).max(min)
+                    };
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Press { button, .. }
+                    if button == measure && self.mode == Mode::Edges => {}
+                PointerEventKind::Release { button, .. }
+                    if button == measure && self.mode == Mode::Edges =>
+                {
+                    // Nothing to drag, but a click still adds the element to the total
+                    let measured = self.selection();
+                    if let Some(total) = &mut self.running_total {
+                        total.add(measured);
+                        self.request_redraw(qh);
                     }
                 }
-                PointerEventKind::Press { button: 272, .. } => {
-                    // Start drag
+                PointerEventKind::Press { button, .. }
+                    if button == measure && self.hovered_handle().is_some() =>
+                {
+                    // Grab the drawn rectangle's side or corner; the rest stays put
+                    self.resizing = self.hovered_handle();
+                    self.is_dragging = true;
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Press { button, .. } if button == measure && self.path_mode => {
+                    // Start recording a new path
+                    self.path = vec![(self.pointer_x, self.pointer_y)];
+                    self.is_dragging = true;
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Press { button, .. } if button == measure => {
+                    // Start drag, keeping the previous rectangle pinned if Shift is held
+                    if self.modifiers.shift {
+                        self.pinned_rects.extend(self.drag_rect);
+                    } else {
+                        self.pinned_rects.clear();
+                    }
                     self.drag_start = Some((self.pointer_x, self.pointer_y));
                     self.is_dragging = true;
                     self.drag_rect = None;
-                    self.needs_redraw = true;
-                    if let Some(ref layer_surface) = self.layer_surface {
-                        layer_surface
-                            .wl_surface()
-                            .frame(qh, layer_surface.wl_surface().clone());
-                        layer_surface.wl_surface().commit();
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Press { button, .. } if button == dismiss && self.is_dragging => {
+                    // Dismiss cancels a drag in progress
+                    self.cancel_drag(qh);
+                }
+                PointerEventKind::Press { button, .. } if Some(button) == exit => {
+                    // Exiting by mouse is opt-in via `--exit-button`
+                    self.exit = true;
+                }
+                PointerEventKind::Release { button, .. } if button == measure && self.path_mode => {
+                    // Keep the finished path on screen; a click without movement clears it
+                    if self.path.len() < 2 {
+                        self.path.clear();
                     }
+                    self.is_dragging = false;
+                    self.request_redraw(qh);
                 }
-                PointerEventKind::Release { button: 272, .. } => {
+                PointerEventKind::Release { button, .. }
+                    if button == measure && self.resizing.is_some() =>
+                {
+                    // Keep the resized rectangle as placed by hand, without snapping
+                    if let Some((x1, y1, x2, y2)) = self.drag_corners() {
+                        self.drag_rect = Some(normalize_rect(x1, y1, x2, y2));
+                    }
+                    self.resizing = None;
+                    self.is_dragging = false;
+                    self.update_cursor_shape();
+                    self.request_redraw(qh);
+                }
+                PointerEventKind::Release { button, .. } if button == measure => {
                     // End drag - finalize rectangle only if it has size
-                    if let Some((start_x, start_y)) = self.drag_start {
-                        let (left, top, right, bottom) = normalize_rect(
-                            to_physical(start_x, self.scale),
-                            to_physical(start_y, self.scale),
-                            to_physical(self.pointer_x, self.scale),
-                            to_physical(self.pointer_y, self.scale),
-                        );
-                        if right > left && bottom > top {
+                    if let Some((x1, y1, x2, y2)) = self.drag_corners() {
+                        let (left, top, right, bottom) = normalize_rect(x1, y1, x2, y2);
+                        if right > left && bottom > top && self.grid_snap {
+                            // Round corners to the grid; right/bottom are inclusive so snap the
+                            // pixel boundary just past them
+                            let grid = self.config.grid_size;
+                            let left = snap_to_grid(left, grid, self.scale);
+                            let top = snap_to_grid(top, grid, self.scale);
+                            let right = snap_to_grid(right + 1, grid, self.scale).saturating_sub(1);
+                            let bottom =
+                                snap_to_grid(bottom + 1, grid, self.scale).saturating_sub(1);
+                            self.drag_rect = Some(normalize_rect(left, top, right, bottom));
+                        } else if right > left && bottom > top {
                             // Snap each edge inward to nearby content
                             let snapped_left = snap_edge_x(&self.screenshot, left, top, bottom, 1);
                             let snapped_right =
@@ -599,14 +2406,19 @@ impl PointerHandler for WaylandApp {
                             self.drag_rect = None;
                         }
                     }
-                    self.is_dragging = false;
-                    self.needs_redraw = true;
-                    if let Some(ref layer_surface) = self.layer_surface {
-                        layer_surface
-                            .wl_surface()
-                            .frame(qh, layer_surface.wl_surface().clone());
-                        layer_surface.wl_surface().commit();
+                    // While adding up measurements, add the finished rectangle, or for a
+                    // click the element under the cursor
+                    if self.running_total.is_some() {
+                        let measured = match self.drag_rect {
+                            Some(rect) => Some(rect),
+                            None => (self.mode != Mode::Drag).then(|| self.selection()),
+                        };
+                        if let (Some(total), Some(rect)) = (&mut self.running_total, measured) {
+                            total.add(rect);
+                        }
                     }
+                    self.is_dragging = false;
+                    self.request_redraw(qh);
                 }
                 _ => {}
             }
@@ -614,6 +2426,66 @@ impl PointerHandler for WaylandApp {
     }
 }
 
+impl RelativePointerHandler for WaylandApp {
+    fn relative_pointer_motion(
+        &mut self,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+        _: &ZwpRelativePointerV1,
+        _: &wl_pointer::WlPointer,
+        event: RelativeMotionEvent,
+    ) {
+        if self.precision_lock.is_none() {
+            return;
+        }
+        self.last_activity = Instant::now();
+        let step = PRECISION_FACTOR / self.scale;
+        let (dx, dy) = event.delta_unaccel;
+        let x = (self.pointer_x + dx * step).clamp(0.0, self.width.saturating_sub(1) as f64);
+        let y = (self.pointer_y + dy * step).clamp(0.0, self.height.saturating_sub(1) as f64);
+        self.move_pointer(x, y, qh);
+    }
+}
+
+impl PointerConstraintsHandler for WaylandApp {
+    fn confined(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpConfinedPointerV1,
+        _: &wl_surface::WlSurface,
+        _: &wl_pointer::WlPointer,
+    ) {
+    }
+    fn unconfined(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpConfinedPointerV1,
+        _: &wl_surface::WlSurface,
+        _: &wl_pointer::WlPointer,
+    ) {
+    }
+    fn locked(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpLockedPointerV1,
+        _: &wl_surface::WlSurface,
+        _: &wl_pointer::WlPointer,
+    ) {
+    }
+    fn unlocked(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpLockedPointerV1,
+        _: &wl_surface::WlSurface,
+        _: &wl_pointer::WlPointer,
+    ) {
+    }
+}
+
 impl ShmHandler for WaylandApp {
     fn shm_state(&mut self) -> &mut Shm {
         &mut self.shm
@@ -634,7 +2506,11 @@ delegate_seat!(WaylandApp);
 delegate_keyboard!(WaylandApp);
 delegate_pointer!(WaylandApp);
 delegate_layer!(WaylandApp);
+delegate_xdg_shell!(WaylandApp);
+delegate_xdg_window!(WaylandApp);
 delegate_registry!(WaylandApp);
+delegate_relative_pointer!(WaylandApp);
+delegate_pointer_constraints!(WaylandApp);
 
 // Fractional scaling protocol handlers
 impl Dispatch<WpFractionalScaleManagerV1, ()> for WaylandApp {
@@ -693,3 +2569,113 @@ impl Dispatch<WpViewport, ()> for WaylandApp {
     ) {
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_physical_rounds_at_fractional_scale() {
+        // At 1.5x, logical 0.34 lands at physical 0.51 and 1.0 at exactly 1.5
+        assert_eq!(to_physical(0.34, 1.5, 100), 1);
+        assert_eq!(to_physical(1.0, 1.5, 100), 2);
+        assert_eq!(to_physical(1.3, 1.5, 100), 2);
+        assert_eq!(to_physical(1.7, 1.5, 100), 3);
+        // Truncation would report the pixel to the left/above for each of these
+        for logical in [0.34, 1.0, 1.7] {
+            assert_eq!(to_physical(logical, 1.5, 100), (logical * 1.5) as u32 + 1);
+        }
+    }
+
+    #[test]
+    fn to_physical_is_exact_at_integer_scale() {
+        assert_eq!(to_physical(10.0, 2.0, 100), 20);
+        assert_eq!(to_physical(10.0, 1.0, 100), 10);
+    }
+
+    #[test]
+    fn to_physical_stays_within_the_capture() {
+        // The last half logical pixel of a 1.5x, 100px capture rounds to 100, one past it
+        assert_eq!(to_physical(66.5, 1.5, 100), 99);
+        assert_eq!(to_physical(66.0, 1.5, 100), 99);
+        assert_eq!(to_physical(65.5, 1.5, 100), 98);
+    }
+
+    #[test]
+    fn resize_handles_grab_nearby_sides() {
+        let rect = (100, 100, 200, 150);
+        let handle = |x, y| resize_handle(rect, x, y, 6);
+        // Corners grab both sides, edges one, and the inside or far away nothing
+        assert_eq!(
+            handle(98, 153),
+            Some(ResizeHandle {
+                left: true,
+                right: false,
+                top: false,
+                bottom: true,
+            })
+        );
+        assert_eq!(
+            handle(205, 120).map(|h| (h.right, h.top)),
+            Some((true, false))
+        );
+        assert_eq!(handle(150, 125), None);
+        assert_eq!(handle(150, 170), None);
+        assert_eq!(handle(215, 100), None);
+        // On a thin rectangle the nearer side wins
+        let thin = resize_handle((100, 100, 104, 150), 103, 120, 6).unwrap();
+        assert!(thin.right && !thin.left);
+    }
+
+    #[test]
+    fn resize_cursor_points_along_the_grabbed_sides() {
+        use wp_cursor_shape_device_v1::Shape;
+        let handle = |left, right, top, bottom| ResizeHandle {
+            left,
+            right,
+            top,
+            bottom,
+        };
+        assert_eq!(
+            handle(true, false, true, false).cursor_shape(),
+            Shape::NwseResize
+        );
+        assert_eq!(
+            handle(false, true, true, false).cursor_shape(),
+            Shape::NeswResize
+        );
+        assert_eq!(
+            handle(false, true, false, false).cursor_shape(),
+            Shape::EwResize
+        );
+        assert_eq!(
+            handle(false, false, false, true).cursor_shape(),
+            Shape::NsResize
+        );
+    }
+
+    #[test]
+    fn watch_file_is_replaced_whole() {
+        let path = std::env::temp_dir().join(format!("hypruler-watch-{}", std::process::id()));
+        write_watch_file(&path, "120x48+10+20").unwrap();
+        write_watch_file(&path, "64x64+0+0").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "64x64+0+0\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn watch_pipe_without_reader_is_skipped() {
+        let path = std::env::temp_dir().join(format!("hypruler-pipe-{}", std::process::id()));
+        rustix::fs::mknodat(
+            rustix::fs::CWD,
+            &path,
+            rustix::fs::FileType::Fifo,
+            rustix::fs::Mode::RUSR | rustix::fs::Mode::WUSR,
+            0,
+        )
+        .unwrap();
+        let written = write_watch_file(&path, "120x48+10+20");
+        std::fs::remove_file(&path).unwrap();
+        assert!(written.is_ok());
+    }
+}